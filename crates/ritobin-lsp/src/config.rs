@@ -1,4 +1,9 @@
-use std::{collections::HashMap, fmt, iter, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt, iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use itertools::Itertools as _;
 use paths::AbsPathBuf;
@@ -71,10 +76,53 @@ pub struct InitOptions {
     pub hash_path: Option<PathBuf>,
     #[serde(deserialize_with = "empty_string_as_none")]
     pub meta_dump_path: Option<PathBuf>,
+    /// JSON file or Markdown directory with class and field documentation
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub docs_path: Option<PathBuf>,
+    /// Listing of the files in the game's WADs, offered when completing asset paths
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub wad_listing_path: Option<PathBuf>,
+    /// Folders that asset paths are resolved against, e.g. extracted WADs
+    #[serde(default)]
+    pub asset_roots: Vec<PathBuf>,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsOptions,
+    #[serde(default)]
+    pub inlay_hints: InlayHintsOptions,
     #[serde(flatten)]
     pub other: HashMap<String, serde_json::Value>,
 }
 
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiagnosticsOptions {
+    /// Maximum number of diagnostics published per document, unlimited if unset.
+    pub max_count: Option<usize>,
+    /// Report fields that have no default value but are never set.
+    pub missing_fields: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InlayHintsOptions {
+    /// Show the names of `0x..` hashes found in the hash tables.
+    pub unhashed_names: bool,
+    /// Show the value type of map entries.
+    pub types: bool,
+    /// Label the components of vector and colour values.
+    pub vector_components: bool,
+}
+
+impl Default for InlayHintsOptions {
+    fn default() -> Self {
+        Self {
+            unhashed_names: true,
+            types: true,
+            vector_components: true,
+        }
+    }
+}
+
 fn empty_string_as_none<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
 where
     D: Deserializer<'de>,
@@ -136,10 +184,53 @@ impl Config {
         &self.root_path
     }
 
+    pub fn workspace_roots(&self) -> &[AbsPathBuf] {
+        &self.workspace_roots
+    }
+
     pub fn caps(&self) -> &ClientCapabilities {
         &self.caps
     }
 
+    pub fn diagnostics_max_count(&self) -> Option<usize> {
+        self.initialization_options
+            .as_ref()
+            .and_then(|o| o.diagnostics.max_count)
+    }
+
+    pub fn diagnostics_missing_fields(&self) -> bool {
+        self.initialization_options
+            .as_ref()
+            .is_some_and(|o| o.diagnostics.missing_fields)
+    }
+
+    /// Folders that asset paths are resolved against, relative ones being relative to the
+    /// root path. Defaults to the workspace roots.
+    pub fn asset_roots(&self) -> Vec<PathBuf> {
+        let roots = self
+            .initialization_options
+            .as_ref()
+            .map_or(&[][..], |o| &o.asset_roots);
+        match roots.is_empty() {
+            true => self
+                .workspace_roots
+                .iter()
+                .map(|root| AsRef::<Path>::as_ref(root).to_path_buf())
+                .collect(),
+            false => {
+                let root_path: &Path = self.root_path.as_ref();
+                roots.iter().map(|root| root_path.join(root)).collect()
+            }
+        }
+    }
+
+    pub fn inlay_hints(&self) -> InlayHintsOptions {
+        self.initialization_options
+            .as_ref()
+            .map(|o| o.inlay_hints)
+            .unwrap_or_default()
+    }
+
     // VSCode is our reference implementation, so we allow ourselves to work around issues by
     // special casing certain versions
     pub fn visual_studio_code_version(&self) -> Option<&Version> {
@@ -0,0 +1,173 @@
+//! User supplied documentation for classes and fields, shown alongside what the meta dump
+//! tells us.
+//!
+//! Docs are read from either a JSON file:
+//!
+//! ```json
+//! {
+//!     "SkinCharacterDataProperties": {
+//!         "description": "Top level skin data.",
+//!         "fields": { "skinClassification": "Whether the skin is a legacy skin." }
+//!     }
+//! }
+//! ```
+//!
+//! or a directory of Markdown files, one per class named `<ClassName>.md`. Text before the
+//! first `## ` heading documents the class, and each `## fieldName` section documents a field.
+//!
+//! Class and field names may also be written as `0x..` hashes.
+
+use std::{fs, path::Path};
+
+use anyhow::Context as _;
+use ritobin_lsp::syntax::hash_name;
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+
+use crate::lol_meta::service::Classes;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonClassDocs {
+    description: Option<String>,
+    fields: FxHashMap<String, String>,
+}
+
+/// Documentation for one class, keyed by name hash.
+#[derive(Debug, Default)]
+pub struct ClassDocs {
+    pub description: Option<String>,
+    pub fields: FxHashMap<u32, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Docs {
+    classes: FxHashMap<u32, ClassDocs>,
+}
+
+impl Docs {
+    /// Load docs from a JSON file, or from a directory of Markdown files.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match path.is_dir() {
+            true => Self::load_markdown_dir(path),
+            false => Self::load_json(path),
+        }
+    }
+
+    fn load_json(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("reading {path:?}"))?;
+        let json: FxHashMap<String, JsonClassDocs> = serde_json::from_str(&text)?;
+        let classes = json
+            .into_iter()
+            .map(|(class, docs)| {
+                let fields = docs
+                    .fields
+                    .into_iter()
+                    .map(|(field, doc)| (hash_name(&field), doc))
+                    .collect();
+                (
+                    hash_name(&class),
+                    ClassDocs {
+                        description: docs.description,
+                        fields,
+                    },
+                )
+            })
+            .collect();
+        Ok(Self { classes })
+    }
+
+    fn load_markdown_dir(dir: &Path) -> anyhow::Result<Self> {
+        let mut classes = FxHashMap::default();
+        for entry in fs::read_dir(dir).with_context(|| format!("reading {dir:?}"))? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            let Some(class) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let text = fs::read_to_string(&path).with_context(|| format!("reading {path:?}"))?;
+            classes.insert(hash_name(class), ClassDocs::from_markdown(&text));
+        }
+        Ok(Self { classes })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    pub fn class(&self, class: u32) -> Option<&str> {
+        self.classes.get(&class)?.description.as_deref()
+    }
+
+    /// Docs for `field`, from the first class in the base chain that documents it.
+    pub fn field(&self, classes: &Classes, class: u32, field: u32) -> Option<&str> {
+        // the class itself may be documented without being in the dump
+        let bases = classes.ancestors(class).skip(1).map(|(hash, _)| *hash);
+        std::iter::once(class).chain(bases).find_map(|class| {
+            self.classes
+                .get(&class)
+                .and_then(|c| c.fields.get(&field))
+                .map(|doc| doc.as_str())
+        })
+    }
+}
+
+impl ClassDocs {
+    fn from_markdown(text: &str) -> Self {
+        let mut docs = Self::default();
+        let mut field = None;
+        let mut section = String::new();
+        let mut flush = |field: Option<&str>, section: &mut String| {
+            let doc = section.trim().to_owned();
+            section.clear();
+            if doc.is_empty() {
+                return;
+            }
+            match field {
+                Some(field) => {
+                    docs.fields.insert(hash_name(field), doc);
+                }
+                None => docs.description = Some(doc),
+            }
+        };
+
+        for line in text.lines() {
+            if let Some(heading) = line.strip_prefix("## ") {
+                flush(field, &mut section);
+                field = Some(heading.trim().trim_matches('`'));
+                continue;
+            }
+            section.push_str(line);
+            section.push('\n');
+        }
+        flush(field, &mut section);
+        docs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ltk_hash::fnv1a;
+
+    use super::*;
+
+    #[test]
+    fn markdown_sections() {
+        let docs = ClassDocs::from_markdown(
+            "Skin data.\n\n## skinClassification\nLegacy or not.\n\n## `0x12345678`\nUnknown field.\n",
+        );
+        assert_eq!(docs.description.as_deref(), Some("Skin data."));
+        assert_eq!(
+            docs.fields
+                .get(&fnv1a::hash_lower("skinclassification"))
+                .map(String::as_str),
+            Some("Legacy or not.")
+        );
+        assert_eq!(
+            docs.fields.get(&0x12345678).map(String::as_str),
+            Some("Unknown field.")
+        );
+    }
+}
@@ -1,11 +1,14 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use anyhow::Result;
 use lsp_types::notification::Notification as _;
 use lsp_types::request::Request as _;
 use lsp_types::{
-    DidChangeTextDocumentParams, DidOpenTextDocumentParams,
-    notification::{DidChangeTextDocument, DidOpenTextDocument},
+    DidChangeTextDocumentParams, DidChangeWatchedFilesParams, DidOpenTextDocumentParams,
+    DidSaveTextDocumentParams,
+    notification::{
+        DidChangeTextDocument, DidChangeWatchedFiles, DidOpenTextDocument, DidSaveTextDocument,
+    },
 };
 
 use crate::{server::Server, worker::Worker};
@@ -54,7 +57,39 @@ pub async fn notification(server: &Arc<Server>, note: &lsp_server::Notification)
             //     docs.insert(uri.clone(), doc);
             // }
         }
+        DidChangeWatchedFiles::METHOD => {
+            let p: DidChangeWatchedFilesParams = serde_json::from_value(note.params.clone())?;
+            let paths = p
+                .changes
+                .into_iter()
+                .filter_map(|change| change.uri.to_file_path().ok())
+                .collect::<Vec<_>>();
+            update_workspace(server, paths);
+        }
+        DidSaveTextDocument::METHOD => {
+            let p: DidSaveTextDocumentParams = serde_json::from_value(note.params.clone())?;
+            if let Ok(path) = p.text_document.uri.to_file_path() {
+                update_workspace(server, vec![path]);
+            }
+        }
         _ => {}
     }
     Ok(())
 }
+
+/// Re-read `paths` into the workspace index, and re-check the open documents if that changed
+/// which bin objects their links can resolve to.
+fn update_workspace(server: &Arc<Server>, paths: Vec<PathBuf>) {
+    server.asset_files.write().clear();
+    let server = server.clone();
+    tokio::spawn(async move {
+        let objects_changed = tokio::task::spawn_blocking({
+            let server = server.clone();
+            move || server.workspace.update(&server, paths)
+        })
+        .await;
+        if objects_changed.unwrap_or(false) {
+            server.revalidate_open_documents().await;
+        }
+    });
+}
@@ -1,22 +1,34 @@
+use std::sync::Arc;
+
 use anyhow::Result;
 use itertools::Itertools;
 use lsp_server::Request as ServerRequest;
 use lsp_types::request::Request;
-use lsp_types::{CompletionParams, notification::Notification as _};
 use lsp_types::{
-    DocumentFormattingParams, SemanticTokensParams, SemanticTokensRangeParams,
+    CodeActionParams, ColorPresentationParams, DocumentColorParams, DocumentDiagnosticParams,
+    DocumentFormattingParams, DocumentLinkParams, InlayHintParams, SemanticTokensParams,
+    SemanticTokensRangeParams, SignatureHelpParams, WorkspaceDiagnosticParams,
+    WorkspaceDiagnosticReportResult,
     request::{
-        Completion, Formatting, HoverRequest, SemanticTokensFullRequest, SemanticTokensRangeRequest,
+        ColorPresentationRequest, Completion, DocumentColor, DocumentDiagnosticRequest,
+        DocumentLinkRequest, Formatting, HoverRequest, InlayHintRequest, ResolveCompletionItem,
+        SemanticTokensFullRequest, SemanticTokensRangeRequest, SignatureHelpRequest,
+        WorkspaceDiagnosticRequest,
     },
 };
+use lsp_types::{CompletionItem, CompletionParams, notification::Notification as _};
+use rustc_hash::FxHashSet;
 
 use crate::{
-    lsp::ext::{HoverParams, Unhash, UnhashParams},
+    lsp::ext::{
+        CodeAction, CodeActionRequest, CodeActionResolveRequest, CompletionResolveData,
+        HoverParams, Rehash, RehashParams, Unhash, UnhashParams,
+    },
     server::Server,
     worker::{self, CompletionRequest},
 };
 
-pub async fn request(server: &Server, req: ServerRequest) -> Result<()> {
+pub async fn request(server: &Arc<Server>, req: ServerRequest) -> Result<()> {
     // tracing::debug!(?req, "handle_request");
     let id = req.id.clone();
     let (uri, msg) = {
@@ -34,6 +46,13 @@ pub async fn request(server: &Server, req: ServerRequest) -> Result<()> {
                     worker::Message::UnhashRequest { id, range: p.range },
                 )
             }
+            Rehash::METHOD => {
+                let p: RehashParams = serde_json::from_value(req.params)?;
+                (
+                    p.text_document.uri,
+                    worker::Message::RehashRequest { id, range: p.range },
+                )
+            }
             Completion::METHOD => {
                 let p: CompletionParams = serde_json::from_value(req.params)?;
                 (
@@ -47,6 +66,57 @@ pub async fn request(server: &Server, req: ServerRequest) -> Result<()> {
                     }),
                 )
             }
+            ResolveCompletionItem::METHOD => {
+                let item: CompletionItem = serde_json::from_value(req.params)?;
+                let Some(data) = item
+                    .data
+                    .clone()
+                    .and_then(|d| serde_json::from_value::<CompletionResolveData>(d).ok())
+                else {
+                    server.send_ok(id, &item)?;
+                    return Ok(());
+                };
+                (
+                    data.position.text_document.uri,
+                    worker::Message::CompletionResolve { id, item },
+                )
+            }
+            DocumentColor::METHOD => {
+                let p: DocumentColorParams = serde_json::from_value(req.params)?;
+                (p.text_document.uri, worker::Message::DocumentColor { id })
+            }
+            DocumentLinkRequest::METHOD => {
+                let p: DocumentLinkParams = serde_json::from_value(req.params)?;
+                (p.text_document.uri, worker::Message::DocumentLinks { id })
+            }
+            ColorPresentationRequest::METHOD => {
+                let p: ColorPresentationParams = serde_json::from_value(req.params)?;
+                (
+                    p.text_document.uri,
+                    worker::Message::ColorPresentation {
+                        id,
+                        color: p.color,
+                        range: p.range,
+                    },
+                )
+            }
+            InlayHintRequest::METHOD => {
+                let p: InlayHintParams = serde_json::from_value(req.params)?;
+                (
+                    p.text_document.uri,
+                    worker::Message::InlayHints { id, range: p.range },
+                )
+            }
+            SignatureHelpRequest::METHOD => {
+                let p: SignatureHelpParams = serde_json::from_value(req.params)?;
+                (
+                    p.text_document_position_params.text_document.uri,
+                    worker::Message::SignatureHelp {
+                        id,
+                        position: p.text_document_position_params.position,
+                    },
+                )
+            }
             HoverRequest::METHOD => {
                 let p: HoverParams = serde_json::from_value(req.params.clone())?;
 
@@ -70,6 +140,46 @@ pub async fn request(server: &Server, req: ServerRequest) -> Result<()> {
                     },
                 )
             }
+            DocumentDiagnosticRequest::METHOD => {
+                let p: DocumentDiagnosticParams = serde_json::from_value(req.params)?;
+                (
+                    p.text_document.uri,
+                    worker::Message::DocumentDiagnostic {
+                        id,
+                        previous_result_id: p.previous_result_id,
+                    },
+                )
+            }
+            CodeActionRequest::METHOD => {
+                let p: CodeActionParams = serde_json::from_value(req.params)?;
+                (
+                    p.text_document.uri.clone(),
+                    worker::Message::CodeAction { id, params: p },
+                )
+            }
+            CodeActionResolveRequest::METHOD => {
+                let action: CodeAction = serde_json::from_value(req.params)?;
+                let Some(data) = action.data.as_ref() else {
+                    server.send_err(
+                        id,
+                        lsp_server::ErrorCode::InvalidParams,
+                        "code action has nothing to resolve",
+                    )?;
+                    return Ok(());
+                };
+                (
+                    data.code_action_params.text_document.uri.clone(),
+                    worker::Message::CodeActionResolve { id, action },
+                )
+            }
+            WorkspaceDiagnosticRequest::METHOD => {
+                let p: WorkspaceDiagnosticParams = serde_json::from_value(req.params)?;
+                let open: FxHashSet<_> = server.workers.read().await.keys().cloned().collect();
+                // the index is kept up to date by file change notifications
+                let report = server.workspace.diagnostics(&open, &p.previous_result_ids);
+                server.send_ok(id, &WorkspaceDiagnosticReportResult::Report(report))?;
+                return Ok(());
+            }
             SemanticTokensRangeRequest::METHOD => {
                 let p: SemanticTokensRangeParams = serde_json::from_value(req.params.clone())?;
                 (
@@ -1,5 +1,6 @@
 use ltk_meta::PropertyKind;
 use ltk_ritobin::typecheck::visitor::RitoType;
+use ritobin_lsp::syntax::quote;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::{
@@ -120,6 +121,60 @@ impl Property {
             (None, None) => RitoType::simple(base),
         }
     }
+
+    /// A ritobin literal for this property, from its `default` in the meta dump or a zero value.
+    ///
+    /// `class_name` names [`Property::other_class`], since embeds have to spell out their class.
+    pub fn default_literal(
+        &self,
+        default: Option<&serde_json::Value>,
+        class_name: impl FnOnce(U32Hash) -> String,
+    ) -> String {
+        use serde_json::Value;
+
+        let empty_object = match default {
+            None | Some(Value::Null) => true,
+            Some(Value::Object(fields)) => fields.is_empty(),
+            Some(_) => false,
+        };
+        match (self.value_type, self.other_class) {
+            (BinType::Pointer, _) if matches!(default, None | Some(Value::Null)) => "null".into(),
+            (BinType::Pointer | BinType::Embed, Some(class)) if empty_object => {
+                format!("{} {{}}", class_name(class))
+            }
+            _ => match default {
+                Some(default) => value_literal(default),
+                None => self.value_type.zero_literal().into(),
+            },
+        }
+    }
+}
+
+/// Render a default value from the meta dump as a ritobin literal.
+pub fn value_literal(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(b) => b.to_string(),
+        // floats are f32s widened to f64, so print them at f32 precision
+        Value::Number(n) => match n.as_i64().or_else(|| n.as_u64().map(|n| n as i64)) {
+            Some(n) => n.to_string(),
+            None => (n.as_f64().unwrap_or_default() as f32).to_string(),
+        },
+        Value::String(s) if s.starts_with("0x") => s.clone(),
+        Value::String(s) => quote(s),
+        Value::Array(items) if items.is_empty() => "{}".into(),
+        Value::Array(items) => format!(
+            "{{ {} }}",
+            items
+                .iter()
+                .map(value_literal)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(_) => "{}".into(),
+    }
 }
 
 #[skip_serializing_none]
@@ -188,6 +243,134 @@ pub enum BinType {
     Flag = 0x80 | 7,
 }
 
+impl BinType {
+    /// The type named by a ritobin type expression, e.g. `rgba` or `list2`.
+    pub fn from_rito_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "none" => BinType::None,
+            "bool" => BinType::Bool,
+            "i8" => BinType::I8,
+            "u8" => BinType::U8,
+            "i16" => BinType::I16,
+            "u16" => BinType::U16,
+            "i32" => BinType::I32,
+            "u32" => BinType::U32,
+            "i64" => BinType::I64,
+            "u64" => BinType::U64,
+            "f32" => BinType::F32,
+            "vec2" => BinType::Vec2,
+            "vec3" => BinType::Vec3,
+            "vec4" => BinType::Vec4,
+            "mtx44" => BinType::Mtx44,
+            "rgba" => BinType::Color,
+            "string" => BinType::String,
+            "hash" => BinType::Hash,
+            "file" => BinType::File,
+            "list" => BinType::List,
+            "list2" => BinType::List2,
+            "pointer" => BinType::Pointer,
+            "embed" => BinType::Embed,
+            "link" => BinType::Link,
+            "option" => BinType::Option,
+            "map" => BinType::Map,
+            "flag" => BinType::Flag,
+            _ => return None,
+        })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            BinType::None => "No value",
+            BinType::Bool => "Boolean",
+            BinType::I8 => "Signed 8-bit integer",
+            BinType::U8 => "Unsigned 8-bit integer",
+            BinType::I16 => "Signed 16-bit integer",
+            BinType::U16 => "Unsigned 16-bit integer",
+            BinType::I32 => "Signed 32-bit integer",
+            BinType::U32 => "Unsigned 32-bit integer",
+            BinType::I64 => "Signed 64-bit integer",
+            BinType::U64 => "Unsigned 64-bit integer",
+            BinType::F32 => "32-bit float",
+            BinType::Vec2 => "2D vector of `f32`s",
+            BinType::Vec3 => "3D vector of `f32`s",
+            BinType::Vec4 => "4D vector of `f32`s",
+            BinType::Mtx44 => "4x4 matrix of `f32`s",
+            BinType::Color => "RGBA colour, one `u8` per channel",
+            BinType::String => "Length-prefixed UTF-8 string",
+            BinType::Hash => "FNV-1a hash of a name",
+            BinType::File => "xxh64 hash of a game file path",
+            BinType::List => "List of values",
+            BinType::List2 => "List of values (alternate encoding)",
+            BinType::Pointer => "Pointer to a class instance, may be `null`",
+            BinType::Embed => "Class instance stored inline",
+            BinType::Link => "Reference to another bin object, by its FNV-1a path hash",
+            BinType::Option => "Optional value",
+            BinType::Map => "Map of keys to values",
+            BinType::Flag => "Single bit of a bitfield",
+        }
+    }
+
+    /// Size of a value of this type in a binary bin, if it is fixed.
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            BinType::None => Some(0),
+            BinType::Bool | BinType::I8 | BinType::U8 | BinType::Flag => Some(1),
+            BinType::I16 | BinType::U16 => Some(2),
+            BinType::I32 | BinType::U32 | BinType::F32 | BinType::Color => Some(4),
+            BinType::Hash | BinType::Link => Some(4),
+            BinType::I64 | BinType::U64 | BinType::Vec2 | BinType::File => Some(8),
+            BinType::Vec3 => Some(12),
+            BinType::Vec4 => Some(16),
+            BinType::Mtx44 => Some(64),
+            BinType::String
+            | BinType::List
+            | BinType::List2
+            | BinType::Pointer
+            | BinType::Embed
+            | BinType::Option
+            | BinType::Map => None,
+        }
+    }
+
+    /// Names of the components of vector and colour values.
+    pub fn components(&self) -> Option<&'static [&'static str]> {
+        match self {
+            BinType::Vec2 => Some(&["x", "y"]),
+            BinType::Vec3 => Some(&["x", "y", "z"]),
+            BinType::Vec4 => Some(&["x", "y", "z", "w"]),
+            BinType::Color => Some(&["r", "g", "b", "a"]),
+            _ => None,
+        }
+    }
+
+    /// The literal for the zero value of this type.
+    pub fn zero_literal(&self) -> &'static str {
+        match self {
+            BinType::Bool | BinType::Flag => "false",
+            BinType::I8
+            | BinType::U8
+            | BinType::I16
+            | BinType::U16
+            | BinType::I32
+            | BinType::U32
+            | BinType::I64
+            | BinType::U64
+            | BinType::F32 => "0",
+            BinType::Vec2 => "{ 0, 0 }",
+            BinType::Vec3 => "{ 0, 0, 0 }",
+            BinType::Vec4 => "{ 0, 0, 0, 0 }",
+            BinType::Mtx44 => "{ 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1 }",
+            BinType::Color => "{ 255, 255, 255, 255 }",
+            BinType::String | BinType::File | BinType::Link => "\"\"",
+            BinType::Hash => "0x0",
+            BinType::None | BinType::Pointer => "null",
+            BinType::List | BinType::List2 | BinType::Embed | BinType::Option | BinType::Map => {
+                "{}"
+            }
+        }
+    }
+}
+
 impl From<BinType> for PropertyKind {
     fn from(value: BinType) -> Self {
         match value {
@@ -232,6 +415,18 @@ pub enum ContainerStorage {
     RitoVector,
 }
 
+impl ContainerStorage {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ContainerStorage::UnknownVector => "unknown vector",
+            ContainerStorage::Option => "optional value",
+            ContainerStorage::Fixed => "fixed-size array",
+            ContainerStorage::StdVector => "`std::vector`",
+            ContainerStorage::RitoVector => "Riot vector",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[repr(C)]
 pub enum MapStorage {
@@ -240,3 +435,14 @@ pub enum MapStorage {
     StdUnorderedMap,
     RitoVectorMap,
 }
+
+impl MapStorage {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MapStorage::UnknownMap => "unknown map",
+            MapStorage::StdMap => "`std::map`",
+            MapStorage::StdUnorderedMap => "`std::unordered_map`",
+            MapStorage::RitoVectorMap => "Riot vector map",
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Display,
     fs::File,
     io::{BufReader, Write as _},
@@ -22,24 +22,82 @@ use crate::lol_meta::schema::{Class, DumpFile, Property, U32Hash};
 #[derive(Debug, Default)]
 pub struct Classes(HashMap<U32Hash, Class>);
 impl Classes {
+    #[cfg(test)]
+    pub fn new(classes: HashMap<U32Hash, Class>) -> Self {
+        Self(classes)
+    }
+
     pub fn get(&self, hash: impl Into<U32Hash>) -> Option<&Class> {
         self.0.get(&hash.into())
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (U32Hash, &Class)> {
+        self.0.iter().map(|(hash, class)| (*hash, class))
+    }
+
+    /// `class` followed by its base classes, as far up the chain as the dump goes.
+    pub fn ancestors(&self, class: impl Into<U32Hash>) -> impl Iterator<Item = (U32Hash, &Class)> {
+        let class = class.into();
+        std::iter::successors(self.get(class).map(|c| (class, c)), |(_, c)| {
+            c.base.and_then(|base| Some((base, self.get(base)?)))
+        })
+    }
+
+    /// `class` and every class deriving from it, either through `base` or as one of its
+    /// `secondary_children`.
+    pub fn subclasses(&self, class: impl Into<U32Hash>) -> HashSet<U32Hash> {
+        let class = class.into();
+        let mut found = HashSet::from([class]);
+        let mut queue = vec![class];
+        while let Some(parent) = queue.pop() {
+            let children = self
+                .iter()
+                .filter(|(_, c)| c.base == Some(parent))
+                .map(|(hash, _)| hash)
+                .chain(
+                    self.get(parent)
+                        .into_iter()
+                        .flat_map(|c| c.secondary_children.keys().copied()),
+                )
+                .collect::<Vec<_>>();
+            for child in children {
+                if found.insert(child) {
+                    queue.push(child);
+                }
+            }
+        }
+        found
+    }
     pub fn find_property(
         &self,
         class: impl Into<U32Hash>,
         property: impl Into<U32Hash>,
     ) -> Option<&Property> {
-        let mut search = self.get(class);
+        self.find_declaring(class, property).map(|(_, prop)| prop)
+    }
+
+    /// Like [`Classes::find_property`], along with the class in the base chain that declares
+    /// the property.
+    pub fn find_declaring(
+        &self,
+        class: impl Into<U32Hash>,
+        property: impl Into<U32Hash>,
+    ) -> Option<(U32Hash, &Property)> {
         let property = property.into();
-        while let Some(class) = search {
-            if let Some(prop) = class.properties.get(&property) {
-                return Some(prop);
-            }
+        self.ancestors(class)
+            .find_map(|(hash, class)| Some((hash, class.properties.get(&property)?)))
+    }
 
-            search = class.base.and_then(|base| self.get(base));
-        }
-        None
+    /// The default value of `property`, from the first class in the base chain that declares
+    /// one.
+    pub fn find_default(
+        &self,
+        class: impl Into<U32Hash>,
+        property: impl Into<U32Hash>,
+    ) -> Option<&serde_json::Value> {
+        let property = property.into();
+        self.ancestors(class)
+            .find_map(|(_, class)| class.defaults.as_ref()?.get(&property))
     }
 }
 
@@ -1,8 +1,9 @@
 use lsp_types::{
-    CodeActionKind, CodeActionOptions, CodeActionProviderCapability, CompletionOptions,
-    CompletionOptionsCompletionItem, HoverProviderCapability, OneOf, SaveOptions,
-    SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions, ServerCapabilities,
-    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    CodeActionKind, CodeActionOptions, CodeActionProviderCapability, ColorProviderCapability,
+    CompletionOptions, CompletionOptionsCompletionItem, DocumentLinkOptions,
+    HoverProviderCapability, OneOf, SaveOptions, SemanticTokensFullOptions, SemanticTokensLegend,
+    SemanticTokensOptions, ServerCapabilities, SignatureHelpOptions, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions,
 };
 use rustc_hash::FxHashSet;
 
@@ -156,19 +157,38 @@ pub fn server_capabilities(config: &Config) -> ServerCapabilities {
         // //     "moveItem": true,
         // //     "onEnter": true,
         // // })),
-        // diagnostic_provider: Some(lsp_types::DiagnosticServerCapabilities::Options(
-        //     lsp_types::DiagnosticOptions {
-        //         identifier: Some("ritobin-lsp".to_owned()),
-        //         inter_file_dependencies: true,
-        //         // FIXME
-        //         workspace_diagnostics: false,
-        //         work_done_progress_options: WorkDoneProgressOptions {
-        //             work_done_progress: None,
-        //         },
-        //     },
-        // )),
         // inline_completion_provider: None,
-        completion_provider: Some(CompletionOptions::default()),
+        diagnostic_provider: Some(lsp_types::DiagnosticServerCapabilities::Options(
+            lsp_types::DiagnosticOptions {
+                identifier: Some("ritobin-lsp".to_owned()),
+                inter_file_dependencies: true,
+                workspace_diagnostics: true,
+                work_done_progress_options: Default::default(),
+            },
+        )),
+        code_action_provider: Some(config.caps().code_action_capabilities()),
+        completion_provider: Some(CompletionOptions {
+            resolve_provider: Some(true),
+            trigger_characters: Some(vec![
+                ":".to_owned(),
+                "=".to_owned(),
+                "{".to_owned(),
+                "\n".to_owned(),
+            ]),
+            completion_item: config.caps().completion_item(),
+            ..Default::default()
+        }),
+        inlay_hint_provider: Some(OneOf::Left(true)),
+        color_provider: Some(ColorProviderCapability::Simple(true)),
+        document_link_provider: Some(DocumentLinkOptions {
+            resolve_provider: Some(false),
+            work_done_progress_options: Default::default(),
+        }),
+        signature_help_provider: Some(SignatureHelpOptions {
+            trigger_characters: Some(vec!["{".to_owned(), "\n".to_owned()]),
+            retrigger_characters: Some(vec![",".to_owned(), "=".to_owned(), " ".to_owned()]),
+            work_done_progress_options: Default::default(),
+        }),
         definition_provider: Some(OneOf::Left(false)),
         hover_provider: Some(HoverProviderCapability::Simple(true)),
         document_formatting_provider: Some(OneOf::Left(true)),
@@ -365,6 +385,25 @@ impl ClientCapabilities {
         .is_some()
     }
 
+    /// Whether the client can fill in a code action's edit lazily via `codeAction/resolve`.
+    pub fn code_action_resolve(&self) -> bool {
+        (|| -> _ {
+            Some(
+                self.0
+                    .text_document
+                    .as_ref()?
+                    .code_action
+                    .as_ref()?
+                    .resolve_support
+                    .as_ref()?
+                    .properties
+                    .iter()
+                    .any(|cap_string| cap_string.as_str() == "edit"),
+            )
+        })()
+        .unwrap_or_default()
+    }
+
     pub fn work_done_progress(&self) -> bool {
         (|| -> _ { self.0.window.as_ref()?.work_done_progress })().unwrap_or_default()
     }
@@ -102,6 +102,21 @@ pub struct UnhashParams {
     pub range: Option<Range>,
 }
 
+pub enum Rehash {}
+
+impl Request for Rehash {
+    type Params = RehashParams;
+    type Result = Vec<lsp_types::TextEdit>;
+    const METHOD: &'static str = "ritobin-lsp/rehash";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RehashParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Option<Range>,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ExpandedMacro {
@@ -640,18 +655,14 @@ impl Request for OnTypeFormatting {
     const METHOD: &'static str = "textDocument/onTypeFormatting";
 }
 
+/// `data` of a field completion item, for filling in the rest in `completionItem/resolve`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompletionResolveData {
     pub position: lsp_types::TextDocumentPositionParams,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub imports: Vec<CompletionImport>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub version: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub trigger_character: Option<char>,
-    #[serde(default)]
-    pub for_ref: bool,
-    pub hash: String,
+    /// Hash of the class the field belongs to
+    pub class: u32,
+    /// Hash of the field
+    pub field: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -664,11 +675,6 @@ pub struct InlayHintResolveData {
     pub version: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CompletionImport {
-    pub full_import_path: String,
-}
-
 #[derive(Debug, Deserialize, Default)]
 pub struct ClientCommandOptions {
     pub commands: Vec<String>,
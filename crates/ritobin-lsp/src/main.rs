@@ -25,6 +25,7 @@ use clap::{Parser, Subcommand};
 use crate::{config::Config, lsp::capabilities::server_capabilities};
 
 pub mod config;
+pub mod docs;
 pub mod document;
 pub mod handlers;
 pub mod lol_meta;
@@ -32,6 +33,7 @@ pub mod lsp;
 pub mod main_loop;
 pub mod server;
 pub mod worker;
+pub mod workspace;
 use main_loop::main_loop;
 
 #[derive(Parser, Debug)]
@@ -1,16 +1,25 @@
 use lsp_server::{Connection, Message};
 use lsp_types::notification::Notification as _;
 use lsp_types::request::Request as _;
+use lsp_types::{
+    DidChangeWatchedFilesRegistrationOptions, DocumentFilter, FileSystemWatcher, GlobPattern,
+    Registration, RegistrationParams, TextDocumentRegistrationOptions,
+    TextDocumentSaveRegistrationOptions,
+    notification::{DidChangeWatchedFiles, DidSaveTextDocument},
+    request::RegisterCapability,
+};
 use std::{path::PathBuf, sync::Arc};
 
 use crate::{
     config::Config,
+    docs::Docs,
     handlers,
     lsp::{
         self,
         ext::{ServerStatusNotification, ServerStatusParams},
     },
     server::Server,
+    workspace,
 };
 
 pub async fn main_loop(config: Config, connection: Connection) -> anyhow::Result<()> {
@@ -33,8 +42,51 @@ pub async fn main_loop(config: Config, connection: Connection) -> anyhow::Result
         tracing::error!("Failed to load hashes from {hash_path:?} - {e:?}");
     };
 
+    if let Some(docs_path) = std::env::var("RB_DOCS_PATH")
+        .ok()
+        .and_then(|v| v.parse::<PathBuf>().ok())
+        .or_else(|| {
+            config
+                .initialization_options
+                .as_ref()
+                .and_then(|o| o.docs_path.clone())
+        })
+    {
+        match Docs::load(&docs_path) {
+            Ok(docs) => server.docs = docs,
+            Err(e) => tracing::error!("Failed to load docs from {docs_path:?} - {e:?}"),
+        }
+    }
+
+    if let Some(listing_path) = config
+        .initialization_options
+        .as_ref()
+        .and_then(|o| o.wad_listing_path.clone())
+    {
+        match workspace::load_wad_listing(&listing_path) {
+            Ok(paths) => server.wad_paths = paths,
+            Err(e) => tracing::error!("Failed to load WAD listing from {listing_path:?} - {e:?}"),
+        }
+    }
+
     let server = Arc::new(server);
 
+    // index the workspace up front, so links into files that aren't open can be resolved
+    tokio::spawn({
+        let server = server.clone();
+        async move {
+            let indexed = tokio::task::spawn_blocking({
+                let server = server.clone();
+                move || server.workspace.refresh(&server)
+            })
+            .await;
+            // documents opened in the meantime were checked against an empty index
+            if indexed.is_ok() {
+                server.revalidate_open_documents().await;
+            }
+        }
+    });
+
     tokio::spawn({
         let server = server.clone();
         let meta_override = std::env::var("RB_META_DUMP_PATH")
@@ -50,14 +102,16 @@ pub async fn main_loop(config: Config, connection: Connection) -> anyhow::Result
             match meta_override {
                 Some(meta_override) => {
                     server.meta.load_file(meta_override).await.unwrap();
+                    meta_loaded(&server).await;
                     tracing::info!(
                         "Skipping latest meta dump fetching - dump file path has been explicitly specified."
                     );
                 }
                 None => {
                     let dir = files.cache_dir();
-                    if let Err(e) = server.meta.load(dir).await {
-                        tracing::error!("Failed to load existing meta - {e:?}");
+                    match server.meta.load(dir).await {
+                        Ok(()) => meta_loaded(&server).await,
+                        Err(e) => tracing::error!("Failed to load existing meta - {e:?}"),
                     }
 
                     match server.meta.fetch_latest(dir).await {
@@ -66,6 +120,7 @@ pub async fn main_loop(config: Config, connection: Connection) -> anyhow::Result
                         }
                         Ok(Some(path)) => {
                             server.meta.load_file(path).await.unwrap();
+                            meta_loaded(&server).await;
                         }
                         Ok(None) => {}
                     }
@@ -86,6 +141,7 @@ pub async fn main_loop(config: Config, connection: Connection) -> anyhow::Result
         .conn
         .sender
         .send(lsp_server::Message::Notification(not))?;
+    register_capabilities(&server)?;
 
     for msg in &server.conn.receiver {
         match msg {
@@ -103,8 +159,67 @@ pub async fn main_loop(config: Config, connection: Connection) -> anyhow::Result
                     tracing::error!("[lsp] notification {} failed: {err}", note.method);
                 }
             }
-            Message::Response(resp) => tracing::error!("[lsp] response: {resp:?}"),
+            Message::Response(resp) => tracing::debug!("[lsp] response: {resp:?}"),
         }
     }
     Ok(())
 }
+
+/// Diagnostics computed before the meta dump was loaded are missing every schema check.
+async fn meta_loaded(server: &Arc<Server>) {
+    let _ = tokio::task::spawn_blocking({
+        let server = server.clone();
+        move || server.workspace.recheck(&server)
+    })
+    .await;
+    server.revalidate_open_documents().await;
+}
+
+/// Ask the client to tell us about changes to ritobin files, so the workspace index can be
+/// kept up to date without rescanning the roots.
+fn register_capabilities(server: &Server) -> anyhow::Result<()> {
+    let pattern = format!("**/*.{}", workspace::FILE_EXTENSION);
+    let mut registrations = Vec::new();
+    if server
+        .config
+        .caps()
+        .did_save_text_document_dynamic_registration()
+    {
+        let options = TextDocumentSaveRegistrationOptions {
+            include_text: Some(false),
+            text_document_registration_options: TextDocumentRegistrationOptions {
+                document_selector: Some(vec![DocumentFilter {
+                    language: None,
+                    scheme: Some("file".to_owned()),
+                    pattern: Some(pattern.clone()),
+                }]),
+            },
+        };
+        registrations.push(Registration {
+            id: DidSaveTextDocument::METHOD.to_owned(),
+            method: DidSaveTextDocument::METHOD.to_owned(),
+            register_options: Some(serde_json::to_value(options)?),
+        });
+    }
+    if server
+        .config
+        .caps()
+        .did_change_watched_files_dynamic_registration()
+    {
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String(pattern),
+                kind: None,
+            }],
+        };
+        registrations.push(Registration {
+            id: DidChangeWatchedFiles::METHOD.to_owned(),
+            method: DidChangeWatchedFiles::METHOD.to_owned(),
+            register_options: Some(serde_json::to_value(options)?),
+        });
+    }
+    if !registrations.is_empty() {
+        server.send_request::<RegisterCapability>(RegistrationParams { registrations })?;
+    }
+    Ok(())
+}
@@ -1,13 +1,26 @@
-use std::{io::BufReader, path::Path};
+use std::{
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicI32, Ordering},
+};
 
 use anyhow::Context;
 use lsp_server::{Connection, Message, RequestId, Response};
-use lsp_types::Url;
+use lsp_types::{
+    Url,
+    request::{SemanticTokensRefresh, WorkspaceDiagnosticRefresh},
+};
 use poro_hash::{BinHash, Hashtable};
 use rustc_hash::FxHashMap;
 use tokio::sync::RwLock;
 
-use crate::{config::Config, lol_meta::service::MetaService, worker::WorkerHandle};
+use crate::{
+    config::Config,
+    docs::Docs,
+    lol_meta::service::MetaService,
+    worker::{self, WorkerHandle},
+    workspace::{WadPath, WorkspaceIndex},
+};
 
 #[derive(Default)]
 pub struct Hashes {
@@ -48,6 +61,14 @@ pub struct Server {
     pub workers: RwLock<FxHashMap<Url, WorkerHandle>>,
     pub meta: MetaService,
     pub hashes: Hashes,
+    pub docs: Docs,
+    /// Game file paths from the configured WAD listing
+    pub wad_paths: Vec<WadPath>,
+    pub workspace: WorkspaceIndex,
+    /// The file each asset path was found at under the asset roots, if any, so document links
+    /// don't go to disk on every request. Cleared whenever files change on disk.
+    pub asset_files: dashmap::RwLock<FxHashMap<String, Option<PathBuf>>>,
+    next_request_id: AtomicI32,
 }
 
 impl Server {
@@ -58,6 +79,36 @@ impl Server {
             workers: Default::default(),
             meta: MetaService::default(),
             hashes: Hashes::default(),
+            docs: Docs::default(),
+            wad_paths: Vec::new(),
+            workspace: WorkspaceIndex::default(),
+            asset_files: Default::default(),
+            next_request_id: AtomicI32::new(0),
+        }
+    }
+
+    pub fn send_request<R: lsp_types::request::Request>(
+        &self,
+        params: R::Params,
+    ) -> anyhow::Result<()> {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let req = lsp_server::Request::new(RequestId::from(id), R::METHOD.to_owned(), params);
+        self.conn.sender.send(Message::Request(req))?;
+        Ok(())
+    }
+
+    /// Re-check every open document, once something their diagnostics depend on has changed.
+    pub async fn revalidate_open_documents(&self) {
+        for worker in self.workers.read().await.values() {
+            let _ = worker.tx.send(worker::Message::Revalidate).await;
+        }
+        // unresolved links are highlighted as well
+        if self.config.caps().semantic_tokens_refresh() {
+            let _ = self.send_request::<SemanticTokensRefresh>(());
+        }
+        // clients that pull diagnostics also hold results for files that aren't open
+        if self.config.caps().diagnostics_refresh() {
+            let _ = self.send_request::<WorkspaceDiagnosticRefresh>(());
         }
     }
 
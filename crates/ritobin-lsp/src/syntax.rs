@@ -0,0 +1,431 @@
+//! Flat view over the `key: type = value` entries of a CST, at every nesting level.
+
+use ltk_hash::fnv1a;
+use ltk_ritobin::{
+    cst::{
+        Cst, TreeKind,
+        visitor::{Visit, Visitor, VisitorExt as _},
+    },
+    parse::{Span, Token, TokenKind},
+};
+
+/// A `key: type = value` field or `key = value` map entry.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub span: Span,
+    pub key: Span,
+    pub key_kind: TokenKind,
+    pub type_expr: Option<Span>,
+    pub value: Option<Span>,
+    /// The class name, if the value is a `Class { .. }` block.
+    pub class: Option<Span>,
+    /// Index of the entry whose value contains this one, `None` for top-level entries.
+    pub parent: Option<usize>,
+    /// Index into [`Entries::blocks`] of the block containing this entry.
+    pub block: Option<usize>,
+}
+
+impl Entry {
+    pub fn is_top_level(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    /// Fields are typed, map entries are not.
+    pub fn is_field(&self) -> bool {
+        self.type_expr.is_some()
+    }
+
+    /// The hash of this entry's key, if it is a name, string or hash.
+    pub fn key_hash(&self, text: &str) -> Option<u32> {
+        match KeyId::new(&text[self.key], self.key_kind, true) {
+            KeyId::Hash(hash) => Some(hash),
+            KeyId::Text(_) => None,
+        }
+    }
+}
+
+/// A `{ .. }` block, including the braces.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub span: Span,
+    /// The class name, if this is the body of a `Class { .. }`.
+    pub class: Option<Span>,
+}
+
+#[derive(Debug, Default)]
+pub struct Entries {
+    pub entries: Vec<Entry>,
+    pub blocks: Vec<Block>,
+}
+
+impl Entries {
+    pub fn new(cst: &Cst) -> Self {
+        EntryCollector::default().walk(cst).entries
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Entry)> {
+        self.entries.iter().enumerate()
+    }
+
+    pub fn top_level(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|e| e.is_top_level())
+    }
+
+    pub fn children(&self, parent: usize) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .filter(move |e| e.parent == Some(parent))
+    }
+
+    /// The fields set directly in `block`.
+    pub fn fields_in(&self, block: usize) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .filter(move |e| e.block == Some(block) && e.is_field())
+    }
+
+    /// The bin objects, i.e. the items of the top-level `entries: map[hash, embed]`.
+    pub fn objects<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (usize, &'a Entry)> + 'a {
+        self.iter().filter(move |(_, e)| {
+            e.parent
+                .is_some_and(|p| self.is_objects_map(&self.entries[p], text))
+        })
+    }
+
+    /// Whether `entry` is the top-level `entries` map that holds the bin objects.
+    pub fn is_objects_map(&self, entry: &Entry, text: &str) -> bool {
+        entry.is_top_level() && &text[entry.key] == "entries"
+    }
+
+    /// The innermost entry containing `offset`.
+    pub fn at(&self, offset: u32) -> Option<(usize, &Entry)> {
+        self.iter()
+            .filter(|(_, e)| e.span.start <= offset && offset <= e.span.end)
+            .min_by_key(|(_, e)| e.span.end - e.span.start)
+    }
+
+    /// Tokens of every value that is typed as a `link`, including container items and map
+    /// values.
+    pub fn link_values<'t>(&self, tokens: &'t Tokens, text: &str) -> Vec<&'t Token> {
+        let mut links = Vec::new();
+        for (idx, entry) in self.iter() {
+            let Some(ty) = entry.type_expr.map(|ty| TypeExpr::parse(&text[ty])) else {
+                continue;
+            };
+            if ty.value_type() != "link" {
+                continue;
+            }
+            let values = match ty.is_map() {
+                true => self.children(idx).filter_map(|e| e.value).collect(),
+                false => entry.value.into_iter().collect::<Vec<_>>(),
+            };
+            for value in values {
+                links.extend(
+                    tokens
+                        .in_span(value)
+                        .map(|(t, _)| t)
+                        .filter(|t| matches!(t.kind, TokenKind::String | TokenKind::HexLit)),
+                );
+            }
+        }
+        links
+    }
+}
+
+#[derive(Default)]
+struct EntryCollector {
+    entries: Entries,
+    stack: Vec<TreeKind>,
+    /// Indices of the entries we're currently inside of
+    entry_stack: Vec<usize>,
+    block_stack: Vec<usize>,
+    /// Name of the class tree we most recently entered
+    class_name: Option<Span>,
+}
+
+impl EntryCollector {
+    fn current(&mut self) -> Option<&mut Entry> {
+        let idx = *self.entry_stack.last()?;
+        self.entries.entries.get_mut(idx)
+    }
+
+    fn parent_kind(&self) -> Option<TreeKind> {
+        self.stack.last().copied()
+    }
+}
+
+impl Visitor for EntryCollector {
+    fn enter_tree(&mut self, tree: &Cst) -> Visit {
+        match tree.kind {
+            TreeKind::Entry => {
+                self.entries.entries.push(Entry {
+                    span: tree.span,
+                    key: Span::new(tree.span.start, tree.span.start),
+                    key_kind: TokenKind::Name,
+                    type_expr: None,
+                    value: None,
+                    class: None,
+                    parent: self.entry_stack.last().copied(),
+                    block: self.block_stack.last().copied(),
+                });
+                self.entry_stack.push(self.entries.entries.len() - 1);
+            }
+            TreeKind::TypeExpr if self.parent_kind() == Some(TreeKind::Entry) => {
+                if let Some(entry) = self.current() {
+                    entry.type_expr.get_or_insert(tree.span);
+                }
+            }
+            TreeKind::EntryValue if self.parent_kind() == Some(TreeKind::Entry) => {
+                if let Some(entry) = self.current() {
+                    entry.value.get_or_insert(tree.span);
+                }
+            }
+            TreeKind::Class => {
+                let name = tree.children.first().map(|c| c.span());
+                self.class_name = name;
+                if self.parent_kind() == Some(TreeKind::EntryValue)
+                    && let Some(entry) = self.current()
+                {
+                    entry.class = entry.class.or(name);
+                }
+            }
+            TreeKind::Block => {
+                let class = match self.parent_kind() {
+                    Some(TreeKind::Class) => self.class_name,
+                    _ => None,
+                };
+                self.entries.blocks.push(Block {
+                    span: tree.span,
+                    class,
+                });
+                self.block_stack.push(self.entries.blocks.len() - 1);
+            }
+            _ => {}
+        }
+        self.stack.push(tree.kind);
+        Visit::Continue
+    }
+
+    fn exit_tree(&mut self, tree: &Cst) -> Visit {
+        self.stack.pop();
+        match tree.kind {
+            TreeKind::Entry => {
+                self.entry_stack.pop();
+            }
+            TreeKind::Block => {
+                self.block_stack.pop();
+            }
+            _ => {}
+        }
+        Visit::Continue
+    }
+
+    fn visit_token(&mut self, token: &Token, context: &Cst) -> Visit {
+        if context.kind != TreeKind::EntryKey
+            || matches!(token.kind, TokenKind::Comment | TokenKind::Quote)
+        {
+            return Visit::Continue;
+        }
+        if let Some(entry) = self.current()
+            && entry.key.start == entry.key.end
+        {
+            entry.key = token.span;
+            entry.key_kind = token.kind;
+        }
+        Visit::Continue
+    }
+}
+
+/// Every token in the tree in source order, with the kind of the tree it sits directly in.
+#[derive(Debug, Default)]
+pub struct Tokens(pub Vec<(Token, TreeKind)>);
+
+impl Tokens {
+    pub fn new(cst: &Cst) -> Self {
+        TokenCollector::default().walk(cst).tokens
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Token, TreeKind)> {
+        self.0.iter()
+    }
+
+    /// Tokens that lie entirely within `span`.
+    pub fn in_span(&self, span: Span) -> impl Iterator<Item = &(Token, TreeKind)> {
+        let start = self.0.partition_point(|(t, _)| t.span.start < span.start);
+        self.0[start..]
+            .iter()
+            .take_while(move |(t, _)| t.span.end <= span.end)
+    }
+
+    /// The token containing `offset`.
+    pub fn at(&self, offset: u32) -> Option<&(Token, TreeKind)> {
+        let idx = self.0.partition_point(|(t, _)| t.span.end <= offset);
+        self.0.get(idx).filter(|(t, _)| t.span.start <= offset)
+    }
+}
+
+#[derive(Default)]
+struct TokenCollector {
+    tokens: Tokens,
+}
+
+impl Visitor for TokenCollector {
+    fn visit_token(&mut self, token: &Token, context: &Cst) -> Visit {
+        self.tokens.0.push((*token, context.kind));
+        Visit::Continue
+    }
+}
+
+/// A type expression, e.g. `map[hash, embed]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeExpr<'a> {
+    pub base: &'a str,
+    pub args: Vec<&'a str>,
+}
+
+impl<'a> TypeExpr<'a> {
+    pub fn parse(text: &'a str) -> Self {
+        match text.split_once('[') {
+            Some((base, args)) => Self {
+                base: base.trim(),
+                args: args
+                    .trim_end()
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .collect(),
+            },
+            None => Self {
+                base: text.trim(),
+                args: Vec::new(),
+            },
+        }
+    }
+
+    /// The type of the values held, i.e. the element type of containers and the value type
+    /// of maps.
+    pub fn value_type(&self) -> &'a str {
+        self.args.last().copied().unwrap_or(self.base)
+    }
+
+    pub fn is_map(&self) -> bool {
+        self.base == "map"
+    }
+}
+
+/// Normalised identity of an entry key, so that `mName`, `"mName"` and its `0x..` hash
+/// all compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyId<'a> {
+    Hash(u32),
+    Text(&'a str),
+}
+
+impl<'a> KeyId<'a> {
+    /// `hash_strings` should be false for keys of `string` keyed maps, which are compared
+    /// verbatim.
+    pub fn new(text: &'a str, kind: TokenKind, hash_strings: bool) -> Self {
+        match kind {
+            TokenKind::HexLit => text
+                .strip_prefix("0x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .map_or(Self::Text(text), Self::Hash),
+            TokenKind::Name => Self::Hash(fnv1a::hash_lower(text)),
+            TokenKind::String => {
+                let text = unquote(text);
+                match hash_strings {
+                    true => Self::Hash(fnv1a::hash_lower(text)),
+                    false => Self::Text(text),
+                }
+            }
+            _ => Self::Text(text),
+        }
+    }
+}
+
+/// The hash of a class or field name, which may already be written as a `0x..` hash.
+pub fn hash_name(text: &str) -> u32 {
+    text.strip_prefix("0x")
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .unwrap_or_else(|| fnv1a::hash_lower(unquote(text)))
+}
+
+/// Whether a string looks like a game asset path, e.g. `ASSETS/Characters/Foo/Foo.skn`.
+pub fn is_asset_path(text: &str) -> bool {
+    let Some((dir, file)) = text.rsplit_once('/') else {
+        return false;
+    };
+    !dir.is_empty()
+        && !text.contains(char::is_whitespace)
+        && file
+            .rsplit_once('.')
+            .is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty())
+}
+
+/// Strip the surrounding quotes from a string literal.
+pub fn unquote(text: &str) -> &str {
+    text.strip_prefix(['"', '\''])
+        .and_then(|t| t.strip_suffix(['"', '\'']))
+        .unwrap_or(text)
+}
+
+/// Write `text` as a string literal, the reverse of [`unquote`].
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ids_are_hash_normalised() {
+        let name = KeyId::new("mName", TokenKind::Name, true);
+        let hash = format!("0x{:08x}", fnv1a::hash_lower("mName"));
+        assert_eq!(name, KeyId::new(&hash, TokenKind::HexLit, true));
+        assert_eq!(name, KeyId::new("\"mname\"", TokenKind::String, true));
+        assert_ne!(
+            KeyId::new("\"A\"", TokenKind::String, false),
+            KeyId::new("\"a\"", TokenKind::String, false)
+        );
+    }
+
+    #[test]
+    fn type_exprs() {
+        let ty = TypeExpr::parse("map[ hash, embed ]");
+        assert_eq!(ty.base, "map");
+        assert_eq!(ty.args, ["hash", "embed"]);
+        assert_eq!(ty.value_type(), "embed");
+        assert_eq!(TypeExpr::parse("link").value_type(), "link");
+    }
+
+    #[test]
+    fn asset_paths() {
+        assert!(is_asset_path("ASSETS/Characters/Ahri/Skins/Base/Ahri.skn"));
+        assert!(is_asset_path("data/menu/fontconfig_en_us.txt"));
+        assert!(!is_asset_path("Ahri.skn"));
+        assert!(!is_asset_path("Characters/Ahri/Skins/Skin0"));
+        assert!(!is_asset_path("not a/path.txt"));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        assert_eq!(quote("Characters/Ahri"), "\"Characters/Ahri\"");
+        assert_eq!(quote("a \"b\" \\ c"), r#""a \"b\" \\ c""#);
+        assert_eq!(quote("é"), "\"é\"");
+        assert_eq!(unquote(&quote("plain")), "plain");
+    }
+}
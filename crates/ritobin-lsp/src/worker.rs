@@ -1,42 +1,57 @@
 use std::{
-    fmt::Write as _,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use lsp_server::RequestId;
 use lsp_types::{
-    CompletionContext, CompletionItem, CompletionItemKind, CompletionResponse, FormattingOptions,
-    Hover, MarkedString, MarkupContent, MarkupKind, PartialResultParams, Position, Range,
-    SemanticTokens, TextDocumentContentChangeEvent, TextEdit, Url, WorkDoneProgressParams,
+    CodeActionParams, Color, CompletionContext, CompletionItem, CompletionResponse, Diagnostic,
+    DocumentDiagnosticReportResult, FormattingOptions, Hover, MarkedString, PartialResultParams,
+    Position, Range, SemanticTokens, TextDocumentContentChangeEvent, TextEdit, Url,
+    WorkDoneProgressParams,
 };
-use ltk_hash::fnv1a;
 use ltk_ritobin::{
     Cst,
     cst::{
         Kind as TreeKind, Visitor,
         visitor::{Visit, VisitorExt as _},
     },
-    parse::{Span, Token},
+    parse::{Span, Token, TokenKind},
     print::PrintConfig,
 };
-use poro_hash::BinHash;
-use ritobin_lsp::cst_ext::CstExt as _;
+use ritobin_lsp::syntax::{Entries, Entry, Tokens, TypeExpr, hash_name};
 use similar::TextDiff;
 use tokio::{sync::mpsc, task::JoinHandle};
 
 use crate::{
     document::Document,
-    lol_meta::schema::U32Hash,
-    lsp::{ext::PositionOrRange, semantic_tokens::builder::SemanticTokensBuilder},
+    lol_meta::{
+        schema::{BinType, Property},
+        service::Classes,
+    },
+    lsp::{
+        ext::{self, PositionOrRange},
+        semantic_tokens::builder::SemanticTokensBuilder,
+    },
     server::Server,
-    worker::semantic_tokens::SemanticVisitor,
+    worker::{diagnostics::DiagnosticCollector, semantic_tokens::SemanticVisitor},
 };
 
+pub mod code_actions;
+pub mod colors;
+pub mod completion;
 pub mod diagnostics;
+pub mod document_links;
+pub mod hover;
+pub mod inlay_hints;
+pub mod lints;
 pub mod semantic_tokens;
+pub mod signature_help;
 pub mod unhash;
 
+#[cfg(test)]
+pub(crate) mod fixture;
+
 #[derive(Debug)]
 pub struct CompletionRequest {
     pub id: RequestId,
@@ -52,18 +67,59 @@ pub enum Message {
         id: RequestId,
         range: Option<Range>,
     },
+    RehashRequest {
+        id: RequestId,
+        range: Option<Range>,
+    },
     HoverRequest {
         id: RequestId,
         position: PositionOrRange,
         work_done_progress_params: WorkDoneProgressParams,
     },
     CompletionRequest(CompletionRequest),
+    CompletionResolve {
+        id: RequestId,
+        item: CompletionItem,
+    },
+    DocumentColor {
+        id: RequestId,
+    },
+    DocumentLinks {
+        id: RequestId,
+    },
+    ColorPresentation {
+        id: RequestId,
+        color: Color,
+        range: Range,
+    },
+    InlayHints {
+        id: RequestId,
+        range: Range,
+    },
+    SignatureHelp {
+        id: RequestId,
+        position: Position,
+    },
     FormatRequest {
         id: RequestId,
         options: FormattingOptions,
         work_done_progress_params: WorkDoneProgressParams,
     },
 
+    DocumentDiagnostic {
+        id: RequestId,
+        previous_result_id: Option<String>,
+    },
+
+    CodeAction {
+        id: RequestId,
+        params: CodeActionParams,
+    },
+    CodeActionResolve {
+        id: RequestId,
+        action: ext::CodeAction,
+    },
+
     SemanticTokens {
         id: RequestId,
         work_done_progress_params: WorkDoneProgressParams,
@@ -75,6 +131,9 @@ pub enum Message {
         version: i32,
         changes: Vec<TextDocumentContentChangeEvent>,
     },
+    /// Something the diagnostics depend on changed outside the document, e.g. the workspace
+    /// index or the meta dump.
+    Revalidate,
 }
 
 pub struct WorkerHandle {
@@ -86,6 +145,11 @@ pub struct Worker {
     rx: mpsc::Receiver<Message>,
     document: Document,
     bin: Option<(Cst, ltk_meta::Bin)>,
+    entries: Entries,
+    tokens: Tokens,
+    diagnostics: Vec<Diagnostic>,
+    /// Link values that don't resolve to any known entry
+    unresolved_links: Vec<Span>,
     server: Arc<Server>,
 }
 
@@ -98,6 +162,10 @@ impl Worker {
                 let mut worker = Self {
                     rx,
                     bin: None,
+                    entries: Entries::default(),
+                    tokens: Tokens::default(),
+                    diagnostics: Vec::new(),
+                    unresolved_links: Vec::new(),
                     document: Document::new(uri, version, text),
                     server,
                 };
@@ -113,10 +181,98 @@ impl Worker {
     fn update(&mut self) {
         let cst = Cst::parse(&self.document.text);
         let (bin, errors) = cst.build_bin(&self.document.text);
-        let _ = self.publish_parse_errors(&cst, errors);
+        self.entries = Entries::new(&cst);
+        self.tokens = Tokens::new(&cst);
+
+        let collector = DiagnosticCollector {
+            server: &self.server,
+            document: &self.document,
+        };
+        let links = collector.unresolved_links(&self.entries, &self.tokens);
+        self.diagnostics = collector.collect(&cst, &self.entries, &links, errors);
+        self.unresolved_links = links.into_iter().map(|t| t.span).collect();
+        self.unresolved_links.sort_by_key(|s| s.start);
+        // clients that pull diagnostics will request them themselves
+        if !self.server.config.caps().text_document_diagnostic() {
+            let _ = self.publish_diagnostics();
+        }
         self.bin.replace((cst, bin));
     }
 
+    /// The class hash of the block `entry` is set in.
+    fn entry_class(&self, entry: &Entry) -> Option<u32> {
+        let class = self.entries.blocks.get(entry.block?)?.class?;
+        Some(hash_name(&self.document.text.as_str()[class]))
+    }
+
+    /// The schema property for a class field.
+    fn property<'c>(&self, entry: &Entry, classes: &'c Classes) -> Option<&'c Property> {
+        let field = entry.key_hash(&self.document.text)?;
+        classes.find_property(self.entry_class(entry)?, field)
+    }
+
+    /// Vector and colour values, including container items and map values.
+    fn vector_literals(&self, classes: &Classes) -> Vec<VectorLiteral> {
+        let text = self.document.text.as_str();
+        let mut literals = Vec::new();
+        for (idx, entry) in self.entries.iter() {
+            let Some(value) = entry.value else {
+                continue;
+            };
+            // container items are themselves `{ .. }` blocks inside the value, while map values
+            // are entries of their own
+            let (ty, depth) = match entry.type_expr.map(|ty| TypeExpr::parse(&text[ty])) {
+                Some(ty) if ty.is_map() => continue,
+                Some(ty) => (
+                    self.element_type(idx, classes, false),
+                    1 + !ty.args.is_empty() as u32,
+                ),
+                None => (
+                    entry
+                        .parent
+                        .and_then(|parent| self.element_type(parent, classes, false)),
+                    1,
+                ),
+            };
+            let Some(ty) = ty.filter(|ty| ty.components().is_some()) else {
+                continue;
+            };
+
+            let mut current = 0;
+            let first = literals.len();
+            for (token, _) in self.tokens.in_span(value) {
+                match token.kind {
+                    TokenKind::LCurly => {
+                        current += 1;
+                        if current == depth {
+                            literals.push(VectorLiteral {
+                                ty,
+                                entry: idx,
+                                span: token.span,
+                                components: Vec::new(),
+                            });
+                        }
+                    }
+                    TokenKind::RCurly => {
+                        if current == depth
+                            && let Some(literal) = literals[first..].last_mut()
+                        {
+                            literal.span.end = token.span.end;
+                        }
+                        current -= 1;
+                    }
+                    TokenKind::Number if current == depth => {
+                        if let Some(literal) = literals[first..].last_mut() {
+                            literal.components.push(token.span);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        literals
+    }
+
     pub async fn service(mut self) -> anyhow::Result<()> {
         while let Some(req) = self.rx.recv().await {
             // TODO: propagate err to lsp client instead of killing worker
@@ -127,6 +283,9 @@ impl Worker {
                         .server
                         .send_ok(id, &self.unhash(range)?.unwrap_or_default());
                 }
+                Message::RehashRequest { id, range } => {
+                    let _ = self.server.send_ok(id, &self.rehash(range));
+                }
                 Message::HoverRequest {
                     id,
                     position,
@@ -150,6 +309,26 @@ impl Worker {
                             .unwrap_or_else(|| CompletionResponse::Array(vec![])),
                     );
                 }
+                Message::CompletionResolve { id, item } => {
+                    let _ = self.server.send_ok(id, &self.resolve_completion(item));
+                }
+                Message::DocumentColor { id } => {
+                    let _ = self.server.send_ok(id, &self.document_colors());
+                }
+                Message::DocumentLinks { id } => {
+                    let _ = self.server.send_ok(id, &self.document_links());
+                }
+                Message::ColorPresentation { id, color, range } => {
+                    let _ = self
+                        .server
+                        .send_ok(id, &self.color_presentations(color, range));
+                }
+                Message::InlayHints { id, range } => {
+                    let _ = self.server.send_ok(id, &self.inlay_hints(range));
+                }
+                Message::SignatureHelp { id, position } => {
+                    let _ = self.server.send_ok(id, &self.signature_help(position));
+                }
                 Message::FormatRequest {
                     id,
                     options,
@@ -159,6 +338,28 @@ impl Worker {
                         let _ = self.server.send_ok(id, &res);
                     }
                 }
+                Message::DocumentDiagnostic {
+                    id,
+                    previous_result_id,
+                } => {
+                    let res = self.document_diagnostic(previous_result_id);
+                    let _ = self
+                        .server
+                        .send_ok(id, &DocumentDiagnosticReportResult::Report(res));
+                }
+                Message::CodeAction { id, params } => {
+                    let _ = self.server.send_ok(id, &self.code_actions(params));
+                }
+                Message::CodeActionResolve { id, action } => {
+                    let _ = match self.resolve_code_action(action) {
+                        Some(action) => self.server.send_ok(id, &action),
+                        None => self.server.send_err(
+                            id,
+                            lsp_server::ErrorCode::ContentModified,
+                            "document changed since the code action was listed",
+                        ),
+                    };
+                }
                 Message::SemanticTokens {
                     id,
                     work_done_progress_params,
@@ -177,6 +378,7 @@ impl Worker {
                     self.document.update(version, changes);
                     self.update();
                 }
+                Message::Revalidate => self.update(),
             }
         }
         Ok(())
@@ -197,6 +399,7 @@ impl Worker {
         let visitor = SemanticVisitor {
             text: &doc.text,
             line_nums: &doc.line_numbers,
+            unresolved_links: &self.unresolved_links,
             stack: Vec::new(),
             range: range
                 .as_ref()
@@ -208,188 +411,6 @@ impl Worker {
         Ok(Some(visitor.builder.build()))
     }
 
-    fn complete(&self, req: CompletionRequest) -> anyhow::Result<Option<CompletionResponse>> {
-        let doc = &self.document;
-        let Some((cst, _bin)) = self.bin.as_ref() else {
-            return Ok(None);
-        };
-
-        let class = ClassFinder::new(
-            doc.line_numbers.from_position(&Position::new(
-                req.position.line,
-                req.position.character + 1,
-            )),
-            doc.text.clone(),
-        )
-        .walk(cst);
-
-        let classes = self.server.meta.classes.read();
-        let Some((name, class)) = class
-            .class_stack
-            .last()
-            .map(|(_, class)| {
-                (
-                    &doc.text.as_str()[class],
-                    fnv1a::hash_lower(&doc.text.as_str()[class]),
-                )
-            })
-            .and_then(|(name, hash)| Some((name, classes.get(hash)?)))
-        else {
-            return Ok(None);
-        };
-
-        tracing::debug!("-> {name}");
-
-        let hashes = self.server.hashes.fields.as_ref();
-        if hashes.is_none() {
-            tracing::error!("NO HASHES");
-        }
-
-        let properties = class.properties.iter().map(|(k, prop)| {
-            let label = hashes.and_then(|h| h.hashes.get(&BinHash(**k)).cloned());
-            let type_part = format!(": {}", prop.rito_type());
-            CompletionItem {
-                sort_text: Some(label.clone().unwrap_or_else(|| format!("XXX{:x}", **k))),
-                insert_text: Some(format!(
-                    "{}{type_part} = ",
-                    label.clone().unwrap_or_else(|| k.to_string())
-                )),
-                label: label.unwrap_or_else(|| k.to_string()),
-                label_details: Some(lsp_types::CompletionItemLabelDetails {
-                    detail: Some(type_part),
-                    description: None,
-                }),
-                kind: Some(CompletionItemKind::PROPERTY),
-                ..Default::default()
-            }
-        });
-        Ok(Some(CompletionResponse::Array(properties.collect())))
-    }
-
-    fn hover(
-        &self,
-        position: PositionOrRange,
-        _work_done_progress_params: WorkDoneProgressParams,
-    ) -> anyhow::Result<Option<Hover>> {
-        let pos = position.start();
-        let doc = &self.document;
-        let Some((cst, _bin)) = self.bin.as_ref() else {
-            return Ok(None);
-        };
-
-        let finder =
-            ClassFinder::new(doc.line_numbers.from_position(pos), doc.text.clone()).walk(cst);
-        let classes = self.server.meta.classes.read();
-        let class_name = finder
-            .class_stack
-            .last()
-            .map(|(_, class)| (class, fnv1a::hash_lower(&doc.text.as_str()[class])));
-
-        let markup = match class_name {
-            Some((class_name_span, class_hash)) => {
-                let class_name = &doc.text.as_str()[*class_name_span];
-                let class = classes.get(class_hash);
-
-                MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: match finder.found_token {
-                        Some((token, TreeKind::EntryKey)) => {
-                            let txt = &doc.text.as_str()[token.span];
-                            let hash = fnv1a::hash_lower(txt);
-                            match classes.find_property(class_hash, hash) {
-                                Some(prop) => {
-                                    format!(
-                                        r#"### [{class_name}](https://meta-wiki.leaguetoolkit.dev/classes/{}/)
-
-`{txt}`: `{}`
-
-`0x{:>08x}`
-
-*No documentation available.*
-"#,
-                                        class_name.to_ascii_lowercase(),
-                                        prop.rito_type(),
-                                        hash,
-                                    )
-                                }
-                                None => format!("{txt}: ??"),
-                            }
-                        }
-                        Some((_token, TreeKind::Class)) => match class {
-                            Some(class) => {
-                                let mut str = format!(
-                                    "[{class_name}](https://meta-wiki.leaguetoolkit.dev/classes/{}/) (`0x{:>08x}`)\n\n",
-                                    class_name.to_ascii_lowercase(),
-                                    class_hash,
-                                );
-
-                                let mut base = Some((U32Hash(class_hash), class));
-                                let mut d = 0;
-                                while let Some((hash, class)) = base {
-                                    if d > 0 {
-                                        let base_name = self
-                                            .server
-                                            .hashes
-                                            .types
-                                            .as_ref()
-                                            .and_then(|h| h.hashes.get(&BinHash(*hash)))
-                                            .map(|s| s.as_str())
-                                            .unwrap_or("??");
-                                        writeln!(
-                                            str,
-                                            "{}└─ [{base_name}](https://meta-wiki.leaguetoolkit.dev/classes/{}/)\n",
-                                            "\u{00A0}".repeat(d - 1),
-                                            base_name.to_ascii_lowercase()
-                                        )?;
-                                    }
-                                    d += 1;
-                                    base = class.base.and_then(|b| Some((b, classes.get(b)?)));
-                                }
-
-                                str
-                            }
-                            None => format!("*Unknown class `{class_name}`*"),
-                        },
-                        _ => {
-                            match cst
-                                .find_node(doc.line_numbers.byte_index(pos.line, pos.character + 1))
-                            {
-                                Some((node, tok)) => {
-                                    let txt = &doc.text[tok.span.start as _..tok.span.end as _];
-                                    format!("{txt:?} | {node:?} | {:?}", tok.kind)
-                                }
-                                None => "".into(),
-                            }
-                        }
-                    },
-                }
-            }
-            None => MarkupContent {
-                kind: lsp_types::MarkupKind::PlainText,
-                value: match cst.find_node(doc.line_numbers.byte_index(pos.line, pos.character + 1))
-                {
-                    Some((node, tok)) => {
-                        let txt = &doc.text[tok.span.start as _..tok.span.end as _];
-                        format!("{txt:?} | {node:?} | {:?}", tok.kind)
-                    }
-                    None => "".into(),
-                },
-            },
-        };
-
-        // let txt = match cst.find_node(doc.line_numbers.byte_index(pos.line, pos.character + 1)) {
-        //     Some((node, tok)) => {
-        //         let txt = &doc.text[tok.span.start as _..tok.span.end as _];
-        //         format!("{txt:?} | {node:?} | {:?}", tok.kind)
-        //     }
-        //     None => "".into(),
-        // };
-        Ok(Some(Hover {
-            contents: lsp_types::HoverContents::Markup(markup),
-            range: None,
-        }))
-    }
-
     fn format(
         &mut self,
         _options: FormattingOptions,
@@ -464,6 +485,17 @@ fn diff_to_textedits(original: &str, formatted: &str) -> Vec<TextEdit> {
     edits
 }
 
+/// A `{ .. }` vector or colour value.
+struct VectorLiteral {
+    ty: BinType,
+    /// The entry the value belongs to, i.e. the field or map entry, or the container field for
+    /// container items.
+    entry: usize,
+    span: Span,
+    /// The number tokens in the braces.
+    components: Vec<Span>,
+}
+
 struct ClassFinder {
     stack: Vec<TreeKind>,
     offset: u32,
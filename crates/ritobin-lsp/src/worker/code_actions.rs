@@ -0,0 +1,836 @@
+//! Code actions are listed without their edits, which are only computed once the client
+//! resolves the action the user picked (or eagerly, for clients that can't resolve).
+
+use lsp_types::{
+    CodeActionKind, CodeActionParams, Diagnostic, InsertTextFormat, NumberOrString,
+    OptionalVersionedTextDocumentIdentifier, Range, TextEdit,
+};
+use ltk_ritobin::parse::Span;
+use poro_hash::{BinHash, Hashtable};
+use ritobin_lsp::syntax::hash_name;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    lol_meta::schema::U32Hash,
+    lsp::ext::{
+        CodeAction, CodeActionData, SnippetDocumentChangeOperation, SnippetTextDocumentEdit,
+        SnippetTextEdit, SnippetWorkspaceEdit,
+    },
+    worker::Worker,
+};
+
+/// Which of the collected actions should have their edits computed.
+enum Resolve<'a> {
+    Lazy,
+    All,
+    Only(&'a str),
+}
+
+struct Actions<'a> {
+    worker: &'a Worker,
+    params: &'a CodeActionParams,
+    resolve: Resolve<'a>,
+    actions: Vec<CodeAction>,
+}
+
+impl Actions<'_> {
+    fn add(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        kind: CodeActionKind,
+        preferred: bool,
+        edits: impl FnOnce() -> Vec<TextEdit>,
+    ) {
+        let worker = self.worker;
+        self.push(id, title, kind, preferred, || worker.edit(edits()));
+    }
+
+    /// Add an action whose edit is a snippet with tab stops.
+    fn add_snippet(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        kind: CodeActionKind,
+        edit: impl FnOnce() -> TextEdit,
+    ) {
+        let worker = self.worker;
+        self.push(id, title, kind, false, || worker.snippet_edit(edit()));
+    }
+
+    fn push(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        kind: CodeActionKind,
+        preferred: bool,
+        edit: impl FnOnce() -> SnippetWorkspaceEdit,
+    ) {
+        if let Some(only) = &self.params.context.only
+            && !only.iter().any(|k| kind.as_str().starts_with(k.as_str()))
+        {
+            return;
+        }
+
+        let id = id.into();
+        let resolve = match self.resolve {
+            Resolve::Lazy => false,
+            Resolve::All => true,
+            Resolve::Only(only) => only == id.as_str(),
+        };
+        let edit = resolve.then(edit);
+        let data = edit.is_none().then(|| CodeActionData {
+            code_action_params: self.params.clone(),
+            id,
+            version: Some(self.worker.document.version),
+        });
+        self.actions.push(CodeAction {
+            title: title.into(),
+            kind: Some(kind),
+            edit,
+            is_preferred: preferred.then_some(true),
+            data,
+            ..Default::default()
+        });
+    }
+}
+
+impl Worker {
+    pub fn code_actions(&self, params: CodeActionParams) -> Vec<CodeAction> {
+        let resolve = match self.server.config.caps().code_action_resolve() {
+            true => Resolve::Lazy,
+            false => Resolve::All,
+        };
+        self.collect_code_actions(&params, resolve)
+    }
+
+    /// Fill in the edit of an action from [`Worker::code_actions`], or `None` if the document
+    /// changed since it was listed.
+    pub fn resolve_code_action(&self, action: CodeAction) -> Option<CodeAction> {
+        let data = action.data?;
+        if data.version != Some(self.document.version) {
+            return None;
+        }
+        self.collect_code_actions(&data.code_action_params, Resolve::Only(&data.id))
+            .into_iter()
+            .find(|a| a.edit.is_some())
+    }
+
+    fn collect_code_actions(&self, params: &CodeActionParams, resolve: Resolve) -> Vec<CodeAction> {
+        let range = self.document.line_numbers.from_range(&params.range);
+        let mut acc = Actions {
+            worker: self,
+            params,
+            resolve,
+            actions: Vec::new(),
+        };
+
+        let mut defaults = false;
+        for d in &self.diagnostics {
+            let span = self.document.line_numbers.from_range(&d.range);
+            if span.start > range.end || range.start > span.end {
+                continue;
+            }
+            let Some(NumberOrString::String(code)) = &d.code else {
+                continue;
+            };
+            // the same fix can apply to several diagnostics, and has to be found again on
+            // resolve whatever range the client asks about then
+            let id = diagnostic_id(code, d);
+            match code.as_str() {
+                "default-value" => {
+                    defaults = true;
+                    self.remove_default_fix(&mut acc, id, span)
+                }
+                "schema-type-mismatch" => self.schema_type_fix(&mut acc, id, span),
+                "unexpected-container-item" => self.remove_braces_fix(&mut acc, id, span),
+                "shadowed-entry" | "duplicate-field" | "duplicate-key" => {
+                    self.remove_shadowed_fix(&mut acc, id, d)
+                }
+                "unknown-field" => self.unknown_field_fix(&mut acc, id, span),
+                _ => {}
+            }
+        }
+
+        if defaults {
+            self.remove_all_defaults_fix(&mut acc);
+        }
+        self.fill_missing_fields(&mut acc, range);
+        self.rehash_action(&mut acc, range);
+
+        acc.actions
+    }
+
+    fn remove_default_fix(&self, acc: &mut Actions, id: String, span: Span) {
+        acc.add(
+            id,
+            "Remove field set to its default value",
+            CodeActionKind::QUICKFIX,
+            true,
+            || vec![self.removal(span)],
+        );
+    }
+
+    fn remove_all_defaults_fix(&self, acc: &mut Actions) {
+        let defaults = self
+            .diagnostics
+            .iter()
+            .filter(|d| has_code(d, "default-value"))
+            .map(|d| self.document.line_numbers.from_range(&d.range))
+            .collect::<Vec<_>>();
+        if defaults.len() > 1 {
+            acc.add(
+                "remove_all_defaults",
+                "Remove all fields set to their default value",
+                CodeActionKind::QUICKFIX,
+                false,
+                || defaults.into_iter().map(|s| self.removal(s)).collect(),
+            );
+        }
+    }
+
+    fn schema_type_fix(&self, acc: &mut Actions, id: String, span: Span) {
+        let text = self.document.text.as_str();
+        let Some(entry) = self
+            .entries
+            .entries
+            .iter()
+            .find(|e| e.type_expr == Some(span))
+        else {
+            return;
+        };
+        let (Some(class), Some(key)) = (self.entry_class(entry), entry.key_hash(text)) else {
+            return;
+        };
+        let Some(expected) = self
+            .server
+            .meta
+            .classes
+            .read()
+            .find_property(class, key)
+            .map(|p| p.rito_type().to_string())
+        else {
+            return;
+        };
+
+        acc.add(
+            id,
+            format!("Change type to {expected}"),
+            CodeActionKind::QUICKFIX,
+            true,
+            move || vec![self.replace(span, expected)],
+        );
+    }
+
+    fn remove_braces_fix(&self, acc: &mut Actions, id: String, span: Span) {
+        let text = self.document.text.as_str();
+        let block = self
+            .entries
+            .blocks
+            .iter()
+            .filter(|b| b.class.is_none() && b.span.start <= span.start && span.end <= b.span.end)
+            .min_by_key(|b| b.span.end - b.span.start)
+            .map(|b| b.span)
+            .or_else(|| text[span].starts_with('{').then_some(span));
+        let Some(block) = block else {
+            return;
+        };
+
+        acc.add(
+            id,
+            "Remove curly braces",
+            CodeActionKind::QUICKFIX,
+            true,
+            || {
+                let inner = text[block]
+                    .strip_prefix('{')
+                    .and_then(|t| t.strip_suffix('}'))
+                    .unwrap_or(&text[block]);
+                vec![self.replace(block, inner.trim().to_owned())]
+            },
+        );
+    }
+
+    /// Delete the earlier of two entries with the same key, which is overridden anyway.
+    fn remove_shadowed_fix(&self, acc: &mut Actions, id: String, d: &Diagnostic) {
+        let text = self.document.text.as_str();
+        let Some(first) = d
+            .related_information
+            .as_ref()
+            .and_then(|r| r.first())
+            .map(|r| self.document.line_numbers.from_range(&r.location.range))
+        else {
+            return;
+        };
+        let Some(entry) = self
+            .entries
+            .entries
+            .iter()
+            .find(|e| e.key.start == first.start || e.span.start == first.start)
+        else {
+            return;
+        };
+
+        acc.add(
+            id,
+            format!("Remove shadowed '{}'", &text[entry.key]),
+            CodeActionKind::QUICKFIX,
+            true,
+            || vec![self.removal(entry.span)],
+        );
+    }
+
+    fn unknown_field_fix(&self, acc: &mut Actions, id: String, span: Span) {
+        let text = self.document.text.as_str();
+        let Some(entry) = self.entries.entries.iter().find(|e| e.key == span) else {
+            return;
+        };
+        let Some(class) = self.entry_class(entry) else {
+            return;
+        };
+        let Some(names) = self.server.hashes.fields.as_ref() else {
+            return;
+        };
+
+        let key = &text[span];
+        let classes = self.server.meta.classes.read();
+        let mut closest: Option<(usize, &str)> = None;
+        for (_, class) in classes.ancestors(class) {
+            for hash in class.properties.keys() {
+                let Some(name) = names.hashes.get(&BinHash(hash.0)) else {
+                    continue;
+                };
+                let distance = edit_distance(&key.to_ascii_lowercase(), &name.to_ascii_lowercase());
+                if closest.is_none_or(|(d, _)| distance < d) {
+                    closest = Some((distance, name));
+                }
+            }
+        }
+        // don't suggest something completely unrelated
+        let Some((_, name)) = closest.filter(|(d, _)| *d <= key.len().div_ceil(2)) else {
+            return;
+        };
+
+        acc.add(
+            id,
+            format!("Replace with '{name}'"),
+            CodeActionKind::QUICKFIX,
+            true,
+            || vec![self.replace(span, name.to_owned())],
+        );
+    }
+
+    /// Insert every property of the enclosing class block that isn't set yet.
+    fn fill_missing_fields(&self, acc: &mut Actions, range: Span) {
+        let text = self.document.text.as_str();
+        let Some((idx, block, class_name)) = self
+            .entries
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.span.start < range.start && range.end < b.span.end)
+            .filter_map(|(idx, b)| Some((idx, b, b.class?)))
+            .min_by_key(|(_, b, _)| b.span.end - b.span.start)
+        else {
+            return;
+        };
+        let class = hash_name(&text[class_name]);
+        let classes = self.server.meta.classes.read();
+        let names = |table: Option<&Hashtable<BinHash>>, hash: U32Hash| {
+            table
+                .and_then(|t| t.hashes.get(&BinHash(hash.0)).cloned())
+                .unwrap_or_else(|| format!("0x{:08x}", hash.0))
+        };
+
+        let set: FxHashSet<u32> = self
+            .entries
+            .fields_in(idx)
+            .filter_map(|e| e.key_hash(text))
+            .collect();
+        let mut seen = FxHashSet::default();
+        let mut missing = Vec::new();
+        for (_, c) in classes.ancestors(class) {
+            let mut props = c
+                .properties
+                .keys()
+                .filter(|k| !set.contains(&k.0) && seen.insert(**k))
+                .map(|k| (names(self.server.hashes.fields.as_ref(), *k), *k))
+                .collect::<Vec<_>>();
+            props.sort();
+            missing.extend(props);
+        }
+        if missing.is_empty() {
+            return;
+        }
+
+        // (name, type, value) for each field, with the most derived property winning
+        let fields = missing
+            .into_iter()
+            .filter_map(|(name, hash)| {
+                let prop = classes.find_property(class, hash)?;
+                let value = prop.default_literal(classes.find_default(class, hash), |c| {
+                    names(self.server.hashes.types.as_ref(), c)
+                });
+                Some((name, prop.rito_type().to_string(), value))
+            })
+            .collect::<Vec<_>>();
+
+        let fields_ref = &fields;
+        acc.add(
+            "fill_missing_fields",
+            "Fill missing fields",
+            CodeActionKind::REFACTOR_REWRITE,
+            false,
+            || {
+                vec![
+                    self.insert_fields(
+                        block.span,
+                        idx,
+                        fields_ref
+                            .iter()
+                            .map(|(name, ty, value)| format!("{name}: {ty} = {value}")),
+                    ),
+                ]
+            },
+        );
+        if self.server.config.caps().snippet_text_edit() {
+            acc.add_snippet(
+                "fill_missing_fields_snippet",
+                "Fill missing fields (snippet)",
+                CodeActionKind::REFACTOR_REWRITE,
+                || {
+                    let lines = fields_ref.iter().enumerate().map(|(i, (name, ty, value))| {
+                        format!("{name}: {ty} = ${{{}:{}}}", i + 1, escape_snippet(value))
+                    });
+                    let mut edit = self.insert_fields(block.span, idx, lines);
+                    let end = edit.new_text.trim_end().len();
+                    edit.new_text.insert_str(end, "$0");
+                    edit
+                },
+            );
+        }
+    }
+
+    /// Convert the names in the selection, or the one under the cursor, back to hashes.
+    fn rehash_action(&self, acc: &mut Actions, range: Span) {
+        let (span, title) = match range.start == range.end {
+            true => match self.tokens.at(range.start) {
+                Some((token, _)) => (token.span, "Convert to hash"),
+                None => return,
+            },
+            false => (range, "Convert names in selection to hashes"),
+        };
+        let convertible = self
+            .rehashable(Some(span), &self.server.meta.classes.read())
+            .next()
+            .is_some();
+        if !convertible {
+            return;
+        }
+
+        acc.add(
+            "rehash",
+            title,
+            CodeActionKind::REFACTOR_REWRITE,
+            false,
+            || self.rehash_edits(Some(span)),
+        );
+    }
+
+    /// Insert `lines` at the end of `block`, indented like the block's existing fields.
+    fn insert_fields(
+        &self,
+        block: Span,
+        idx: usize,
+        lines: impl Iterator<Item = String>,
+    ) -> TextEdit {
+        let text = self.document.text.as_str();
+        let indent_of = |offset: usize| {
+            let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+            let line = &text[line_start..];
+            &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+        };
+
+        let block_indent = indent_of(block.start as usize);
+        let unit = match block_indent.contains('\t') {
+            true => "\t",
+            false => "    ",
+        };
+        let indent = match self.entries.fields_in(idx).next() {
+            Some(field) => indent_of(field.span.start as usize).to_owned(),
+            None => format!("{block_indent}{unit}"),
+        };
+        let body = lines.map(|l| format!("{indent}{l}\n")).collect::<String>();
+
+        // the closing brace
+        let close = block.end as usize - 1;
+        let close_line = text[..close].rfind('\n').map_or(0, |i| i + 1);
+        let (at, new_text) = match text[close_line..close].trim().is_empty() {
+            true => (close_line, body),
+            false => (close, format!("\n{body}{block_indent}")),
+        };
+        self.replace(Span::new(at as u32, at as u32), new_text)
+    }
+
+    fn edit(&self, edits: Vec<TextEdit>) -> SnippetWorkspaceEdit {
+        let mut changes = FxHashMap::default();
+        changes.insert(self.document.uri.clone(), edits);
+        SnippetWorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }
+    }
+
+    fn snippet_edit(&self, edit: TextEdit) -> SnippetWorkspaceEdit {
+        SnippetWorkspaceEdit {
+            document_changes: Some(vec![SnippetDocumentChangeOperation::Edit(
+                SnippetTextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: self.document.uri.clone(),
+                        version: Some(self.document.version),
+                    },
+                    edits: vec![SnippetTextEdit {
+                        range: edit.range,
+                        new_text: edit.new_text,
+                        insert_text_format: Some(InsertTextFormat::SNIPPET),
+                        annotation_id: None,
+                    }],
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn replace(&self, span: Span, new_text: String) -> TextEdit {
+        TextEdit {
+            range: self.document.line_numbers.from_span(span),
+            new_text,
+        }
+    }
+
+    /// Deletes `span`, along with the rest of its line if nothing else is on it.
+    fn removal(&self, span: Span) -> TextEdit {
+        let text = self.document.text.as_str();
+        let (start, end) = (span.start as usize, span.end as usize);
+        let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i + 1);
+
+        let span = match text[line_start..start].trim().is_empty()
+            && text[end..line_end].trim().is_empty()
+        {
+            true => Span::new(line_start as u32, line_end as u32),
+            false => span,
+        };
+        self.replace(span, String::new())
+    }
+}
+
+/// Identifies the fix for a diagnostic by the diagnostic's code and range.
+fn diagnostic_id(code: &str, d: &Diagnostic) -> String {
+    let Range { start, end } = d.range;
+    format!(
+        "{code}@{}:{}-{}:{}",
+        start.line, start.character, end.line, end.character
+    )
+}
+
+fn has_code(d: &Diagnostic, code: &str) -> bool {
+    matches!(&d.code, Some(NumberOrString::String(c)) if c == code)
+}
+
+pub(crate) fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{CodeActionContext, TextDocumentIdentifier};
+    use serde_json::json;
+
+    use super::*;
+    use crate::worker::fixture::{Fixture, hex};
+
+    fn fixture() -> Fixture {
+        Fixture::default()
+            .class(
+                "Spell",
+                None,
+                &[
+                    ("mEnabled", "bool"),
+                    ("mCount", "u32"),
+                    ("mName", "string"),
+                    ("mFlag", "bool"),
+                ],
+            )
+            .field_default("Spell", "mEnabled", json!(true))
+            .field_default("Spell", "mFlag", json!(false))
+    }
+
+    const SPELL: &str = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Spell {
+        mEnabled: bool = true
+        mCount: f32 = 2
+        mNmae: string = "a"
+        mName: string = "b"
+        mName: string = "c"
+        mFlag: bool = false
+    }
+}
+"#;
+
+    /// Actions for the first occurrence of `selection` in the document, or for a cursor where
+    /// it has a `|`.
+    fn params(worker: &Worker, selection: &str) -> CodeActionParams {
+        let (before, after) = selection.split_once('|').unwrap_or((selection, ""));
+        let start = worker
+            .document
+            .text
+            .find(&format!("{before}{after}"))
+            .unwrap();
+        let span = match selection.contains('|') {
+            true => Span::new((start + before.len()) as u32, (start + before.len()) as u32),
+            false => Span::new(start as u32, (start + selection.len()) as u32),
+        };
+        CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: worker.document.uri.clone(),
+            },
+            range: worker.document.line_numbers.from_span(span),
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    /// The document after applying the action titled `title`, listed at `selection`.
+    fn apply(worker: &Worker, selection: &str, title: &str) -> String {
+        let action = worker
+            .code_actions(params(worker, selection))
+            .into_iter()
+            .find(|a| a.title == title)
+            .unwrap_or_else(|| panic!("no action '{title}' at '{selection}'"));
+        let mut edits = action
+            .edit
+            .and_then(|e| e.changes)
+            .and_then(|mut c| c.remove(&worker.document.uri))
+            .expect("no edits");
+        let mut text = worker.document.text.clone();
+        edits.sort_by_key(|e| worker.document.line_numbers.from_range(&e.range).start);
+        for edit in edits.into_iter().rev() {
+            let span = worker.document.line_numbers.from_range(&edit.range);
+            text.replace_range(span.start as usize..span.end as usize, &edit.new_text);
+        }
+        text
+    }
+
+    #[test]
+    fn edits_resolve_lazily_when_supported() {
+        let title = "Remove field set to its default value";
+        let eager = fixture().worker(SPELL);
+        let listed = eager.code_actions(params(&eager, "mEnabled"));
+        let remove = listed.iter().find(|a| a.title == title).unwrap();
+        assert!(remove.edit.is_some());
+        assert!(remove.data.is_none());
+
+        let mut lazy = fixture()
+            .capabilities(json!({
+                "textDocument": {
+                    "codeAction": { "resolveSupport": { "properties": ["edit"] } }
+                }
+            }))
+            .worker(SPELL);
+        let listed = lazy.code_actions(params(&lazy, "mEnabled"));
+        assert!(listed.iter().all(|a| a.edit.is_none()));
+        let action = listed.into_iter().find(|a| a.title == title).unwrap();
+        let id = action.data.as_ref().unwrap().id.clone();
+
+        // the id doesn't depend on which other actions were listed alongside
+        let whole = lazy.code_actions(params(&lazy, SPELL));
+        assert_eq!(
+            whole
+                .iter()
+                .filter(|a| a.data.as_ref().is_some_and(|d| d.id == id))
+                .map(|a| a.title.as_str())
+                .collect::<Vec<_>>(),
+            [title]
+        );
+
+        let resolved = lazy.resolve_code_action(action.clone()).unwrap();
+        assert_eq!(resolved.edit, remove.edit);
+
+        lazy.document.version += 1;
+        assert!(lazy.resolve_code_action(action).is_none());
+    }
+
+    #[test]
+    fn schema_type_fix() {
+        let worker = fixture().worker(SPELL);
+        assert_eq!(
+            apply(&worker, "f32", "Change type to u32"),
+            SPELL.replace("mCount: f32", "mCount: u32")
+        );
+    }
+
+    #[test]
+    fn remove_braces_fix() {
+        let text = SPELL.replace("mCount: f32 = 2", "mCount: u32 = { 2 }");
+        let mut worker = fixture().worker(&text);
+        let start = text.find("{ 2 }").unwrap() as u32;
+        worker.diagnostics = vec![Diagnostic {
+            range: worker
+                .document
+                .line_numbers
+                .from_span(Span::new(start, start + 5)),
+            code: Some(NumberOrString::String("unexpected-container-item".into())),
+            ..Default::default()
+        }];
+        assert_eq!(
+            apply(&worker, "{ 2 }", "Remove curly braces"),
+            SPELL.replace("mCount: f32", "mCount: u32")
+        );
+    }
+
+    #[test]
+    fn remove_shadowed_fix() {
+        let worker = fixture().worker(SPELL);
+        assert_eq!(
+            apply(&worker, "mName: string = \"c\"", "Remove shadowed 'mName'"),
+            SPELL.replace("        mName: string = \"b\"\n", "")
+        );
+    }
+
+    #[test]
+    fn closest_field_fix() {
+        let worker = fixture().worker(SPELL);
+        assert_eq!(
+            apply(&worker, "mNmae", "Replace with 'mName'"),
+            SPELL.replace("mNmae", "mName")
+        );
+    }
+
+    #[test]
+    fn remove_all_defaults_fix() {
+        let worker = fixture().worker(SPELL);
+        assert_eq!(
+            apply(
+                &worker,
+                "mEnabled",
+                "Remove all fields set to their default value"
+            ),
+            SPELL
+                .replace("        mEnabled: bool = true\n", "")
+                .replace("        mFlag: bool = false\n", "")
+        );
+    }
+
+    fn leaf_fixture() -> Fixture {
+        Fixture::default()
+            .class("Leaf", None, &[("mName", "string")])
+            .field_default("Leaf", "mName", json!("x"))
+    }
+
+    #[test]
+    fn fill_inherited_fields() {
+        let worker = Fixture::default()
+            .class("Base", None, &[("mId", "hash"), ("mSecret", "u32")])
+            .field_default("Base", "mId", json!("0x1234abcd"))
+            .field_default("Base", "mSecret", json!(7))
+            .class(
+                "Child",
+                Some("Base"),
+                &[("mName", "string"), ("mCount", "u32"), ("mLabel", "string")],
+            )
+            .field_default("Child", "mCount", json!(3))
+            .field_default("Child", "mLabel", json!("a \"b\""))
+            .forget(&["mSecret"])
+            .worker(
+                "entries: map[hash,embed] = {\n    \"Test\" = Child {\n        mName: string = \"x\"\n    }\n}\n",
+            );
+        let filled = format!(
+            "entries: map[hash,embed] = {{
+    \"Test\" = Child {{
+        mName: string = \"x\"
+        mCount: u32 = 3
+        mLabel: string = \"a \\\"b\\\"\"
+        {}: u32 = 7
+        mId: hash = 0x1234abcd
+    }}
+}}
+",
+            hex("mSecret")
+        );
+        assert_eq!(apply(&worker, "mName", "Fill missing fields"), filled);
+    }
+
+    #[test]
+    fn fill_fields_indented_like_the_block() {
+        let worker =
+            leaf_fixture().worker("entries: map[hash,embed] = {\n\t\"Test\" = Leaf {\n\t}\n}\n");
+        assert_eq!(
+            apply(&worker, "\t|}", "Fill missing fields"),
+            "entries: map[hash,embed] = {\n\t\"Test\" = Leaf {\n\t\tmName: string = \"x\"\n\t}\n}\n"
+        );
+
+        let worker =
+            leaf_fixture().worker("entries: map[hash,embed] = {\n  \"Test\" = Leaf {\n  }\n}\n");
+        assert_eq!(
+            apply(&worker, "  |}", "Fill missing fields"),
+            "entries: map[hash,embed] = {\n  \"Test\" = Leaf {\n      mName: string = \"x\"\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn fill_one_line_block() {
+        let worker =
+            leaf_fixture().worker("entries: map[hash,embed] = {\n    \"Test\" = Leaf {}\n}\n");
+        assert_eq!(
+            apply(&worker, "{|}", "Fill missing fields"),
+            "entries: map[hash,embed] = {\n    \"Test\" = Leaf {\n        mName: string = \"x\"\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn fill_fields_snippet() {
+        let worker = leaf_fixture()
+            .capabilities(json!({ "experimental": { "snippetTextEdit": true } }))
+            .worker("entries: map[hash,embed] = {\n    \"Test\" = Leaf {}\n}\n");
+        let action = worker
+            .code_actions(params(&worker, "{|}"))
+            .into_iter()
+            .find(|a| a.title == "Fill missing fields (snippet)")
+            .unwrap();
+        let Some(SnippetDocumentChangeOperation::Edit(edit)) = action
+            .edit
+            .and_then(|e| e.document_changes)
+            .and_then(|c| c.into_iter().next())
+        else {
+            panic!("expected a snippet edit");
+        };
+        assert_eq!(
+            edit.edits[0].new_text,
+            "\n        mName: string = ${1:\"x\"}$0\n    "
+        );
+        assert_eq!(
+            edit.edits[0].insert_text_format,
+            Some(InsertTextFormat::SNIPPET)
+        );
+    }
+}
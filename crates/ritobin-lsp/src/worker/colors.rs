@@ -0,0 +1,239 @@
+use lsp_types::{Color, ColorInformation, ColorPresentation, Range, TextEdit};
+use ltk_ritobin::parse::TokenKind;
+use ritobin_lsp::syntax::unquote;
+
+use crate::{
+    lol_meta::{schema::BinType, service::Classes},
+    worker::{VectorLiteral, Worker, unhash::HashKind},
+};
+
+impl Worker {
+    /// `rgba` values, and `vec4` values of fields named like colours.
+    pub fn document_colors(&self) -> Vec<ColorInformation> {
+        let text = self.document.text.as_str();
+        let classes = self.server.meta.classes.read();
+        self.color_literals(&classes)
+            .into_iter()
+            .filter_map(|literal| {
+                let scale = color_scale(literal.ty);
+                let mut channels = literal
+                    .components
+                    .iter()
+                    .map(|c| text[*c].parse::<f32>().ok().map(|v| v / scale));
+                let [red, green, blue, alpha] = std::array::from_fn(|_| channels.next().flatten());
+                Some(ColorInformation {
+                    range: self.document.line_numbers.from_span(literal.span),
+                    color: Color {
+                        red: red?,
+                        green: green?,
+                        blue: blue?,
+                        alpha: alpha?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Rewrites the colour at `range` with new component values, keeping the original spacing
+    /// and number style.
+    pub fn color_presentations(&self, color: Color, range: Range) -> Vec<ColorPresentation> {
+        let text = self.document.text.as_str();
+        let span = self.document.line_numbers.from_range(&range);
+        let classes = self.server.meta.classes.read();
+        let Some(literal) = self
+            .color_literals(&classes)
+            .into_iter()
+            .find(|l| l.span == span)
+        else {
+            return Vec::new();
+        };
+
+        let scale = color_scale(literal.ty);
+        let channels = [color.red, color.green, color.blue, color.alpha];
+        let mut new_text = String::new();
+        let mut last = literal.span.start;
+        for (component, value) in literal.components.iter().zip(channels) {
+            new_text.push_str(&text[last as usize..component.start as usize]);
+            new_text.push_str(&format_component(
+                value * scale,
+                &text[*component],
+                literal.ty == BinType::Color,
+            ));
+            last = component.end;
+        }
+        new_text.push_str(&text[last as usize..literal.span.end as usize]);
+
+        vec![ColorPresentation {
+            label: new_text.clone(),
+            text_edit: Some(TextEdit { range, new_text }),
+            additional_text_edits: None,
+        }]
+    }
+
+    fn color_literals(&self, classes: &Classes) -> Vec<VectorLiteral> {
+        let mut literals = self.vector_literals(classes);
+        literals.retain(|literal| {
+            literal.components.len() == 4
+                && match literal.ty {
+                    BinType::Color => true,
+                    BinType::Vec4 => self.is_color_field(literal.entry),
+                    _ => false,
+                }
+        });
+        literals
+    }
+
+    /// Whether the field a value belongs to is named like a colour, e.g. `mColor` or
+    /// `birthTint`.
+    fn is_color_field(&self, idx: usize) -> bool {
+        let text = self.document.text.as_str();
+        let mut entry = &self.entries.entries[idx];
+        // map values are named by the map
+        if !entry.is_field()
+            && let Some(parent) = entry.parent
+        {
+            entry = &self.entries.entries[parent];
+        }
+        let key = &text[entry.key];
+        let name = match entry.key_kind {
+            TokenKind::HexLit => u32::from_str_radix(&key[2..], 16)
+                .ok()
+                .and_then(|hash| self.unhashed(HashKind::Field, hash)),
+            _ => Some(unquote(key)),
+        };
+        name.is_some_and(|name| {
+            let name = name.to_ascii_lowercase();
+            ["color", "colour", "tint"]
+                .iter()
+                .any(|word| name.contains(word))
+        })
+    }
+}
+
+/// What a colour channel of 1.0 is written as.
+fn color_scale(ty: BinType) -> f32 {
+    match ty {
+        BinType::Color => 255.0,
+        _ => 1.0,
+    }
+}
+
+/// Writes a channel value with up to three decimals, or rounded if `integer`. Whole numbers
+/// keep the `.0` if `original` had a decimal point.
+fn format_component(value: f32, original: &str, integer: bool) -> String {
+    if integer {
+        return format!("{}", value.round() as i64);
+    }
+    let value = format!("{value:.3}");
+    let value = value.trim_end_matches('0');
+    match value.strip_suffix('.') {
+        Some(int) if original.contains('.') => format!("{int}.0"),
+        Some(int) => int.to_owned(),
+        None => value.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::fixture::{Fixture, hex};
+
+    /// The text each colour covers, with its channels.
+    fn colors(worker: &Worker) -> Vec<(&str, [f32; 4])> {
+        let text = worker.document.text.as_str();
+        worker
+            .document_colors()
+            .into_iter()
+            .map(|info| {
+                let span = worker.document.line_numbers.from_range(&info.range);
+                let Color {
+                    red,
+                    green,
+                    blue,
+                    alpha,
+                } = info.color;
+                (&text[span], [red, green, blue, alpha])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn component_format() {
+        assert_eq!(format_component(127.6, "255", true), "128");
+        assert_eq!(format_component(0.5, "1.0", false), "0.5");
+        assert_eq!(format_component(0.5, "1", false), "0.5");
+        assert_eq!(format_component(1.0, "0.25", false), "1.0");
+        assert_eq!(format_component(1.0, "0", false), "1");
+        assert_eq!(format_component(0.123456, "0.1", false), "0.123");
+    }
+
+    #[test]
+    fn rgba_and_color_named_vec4_values() {
+        let worker = Fixture::default().names(&["mBirthTint"]).worker(&format!(
+            r#"entries: map[hash,embed] = {{
+    "Test" = Spell {{
+        mTint: rgba = {{ 255, 0, 0, 255 }}
+        mColor: vec4 = {{1,  0.5, 0,1}}
+        {}: vec4 = {{ 0, 1, 0, 1 }}
+        mColorMap: map[u8,vec4] = {{
+            1 = {{ 0, 0, 1, 0.5 }}
+        }}
+        mPosition: vec4 = {{ 1, 2, 3, 4 }}
+        mOffsets: map[u8,vec4] = {{
+            1 = {{ 1, 2, 3, 4 }}
+        }}
+    }}
+}}
+"#,
+            hex("mBirthTint")
+        ));
+        assert_eq!(
+            colors(&worker),
+            [
+                ("{ 255, 0, 0, 255 }", [1.0, 0.0, 0.0, 1.0]),
+                ("{1,  0.5, 0,1}", [1.0, 0.5, 0.0, 1.0]),
+                ("{ 0, 1, 0, 1 }", [0.0, 1.0, 0.0, 1.0]),
+                ("{ 0, 0, 1, 0.5 }", [0.0, 0.0, 1.0, 0.5]),
+            ]
+        );
+    }
+
+    #[test]
+    fn presentations_keep_the_spacing() {
+        let worker = Fixture::default().worker(
+            "entries: map[hash,embed] = {\n    \"Test\" = Spell {\n        mTint: rgba = { 255, 0, 0, 255 }\n        mColor: vec4 = {1,  0.5, 0,1}\n    }\n}\n",
+        );
+        let presentations = |at: usize, color| {
+            let range = worker.document_colors()[at].range;
+            worker
+                .color_presentations(color, range)
+                .into_iter()
+                .map(|p| p.label)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            presentations(
+                0,
+                Color {
+                    red: 0.0,
+                    green: 0.0,
+                    blue: 1.0,
+                    alpha: 0.5
+                }
+            ),
+            ["{ 0, 0, 255, 128 }"]
+        );
+        assert_eq!(
+            presentations(
+                1,
+                Color {
+                    red: 0.25,
+                    green: 1.0,
+                    blue: 0.0,
+                    alpha: 1.0
+                }
+            ),
+            ["{0.25,  1.0, 0,1}"]
+        );
+    }
+}
@@ -0,0 +1,974 @@
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionList, CompletionResponse, CompletionTextEdit,
+    Documentation, InsertTextFormat, MarkupContent, MarkupKind, Position, TextDocumentIdentifier,
+    TextDocumentPositionParams, TextEdit,
+};
+use ltk_ritobin::parse::{Span, Token, TokenKind};
+use ritobin_lsp::syntax::{Entry, TypeExpr, hash_name, is_asset_path, quote, unquote};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    lol_meta::{
+        schema::{BinType, Property, U32Hash},
+        service::Classes,
+    },
+    lsp::ext::CompletionResolveData,
+    worker::{CompletionRequest, Worker, code_actions::escape_snippet, unhash::HashKind},
+};
+
+/// A `{` that is still open at the cursor.
+pub(crate) struct OpenBlock {
+    pub brace: Span,
+    /// The class name, if this is the body of a `Class { .. }`.
+    pub class: Option<Span>,
+}
+
+/// What kind of thing is being typed at the cursor, judging by the text before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Site {
+    /// A field name in a class block
+    Field,
+    /// The type expression after `name: `, with the offset of the `:`
+    Type(u32),
+    /// The value after `name: type = `, with the offset of the `=`
+    Value(u32),
+    /// An item of a container value
+    Item,
+}
+
+impl Worker {
+    pub fn complete(&self, req: CompletionRequest) -> anyhow::Result<Option<CompletionResponse>> {
+        let doc = &self.document;
+        let text = doc.text.as_str();
+        let offset = doc.line_numbers.from_position(&req.position);
+        let classes = self.server.meta.classes.read();
+
+        let site = self.site(offset);
+        let before = &text[..offset as usize];
+        // start of what has been typed so far, which completions replace
+        let start = match site {
+            Site::Field | Site::Type(_) => before.trim_end_matches(is_word_char).len(),
+            Site::Value(eq) => {
+                before.len() - text[eq as usize + 1..offset as usize].trim_start().len()
+            }
+            Site::Item => before
+                .trim_end_matches(|c: char| !c.is_whitespace() && c != '{' && c != ',')
+                .len(),
+        };
+        let end = match site {
+            // the closing quote of the string being typed, which editors often insert already
+            Site::Value(_) | Site::Item => {
+                self.string_at(offset).map(|token| token.span.end as usize)
+            }
+            _ => None,
+        }
+        .unwrap_or_else(|| {
+            offset as usize + (text.len() - offset as usize)
+                - text[offset as usize..]
+                    .trim_start_matches(is_word_char)
+                    .len()
+        });
+        let prefix = &text[start..offset as usize];
+
+        let complete = |items| CompletionList {
+            is_incomplete: false,
+            items,
+        };
+        let list = match site {
+            Site::Field => self
+                .field_completions(offset, prefix, req.position, &classes)
+                .map(complete),
+            Site::Value(eq) => self.value_completions(eq, false, prefix, &classes),
+            Site::Item => self.value_completions(offset, true, prefix, &classes),
+            Site::Type(colon) => Some(complete(self.type_completions(colon, &classes))),
+        };
+
+        let range = doc
+            .line_numbers
+            .from_span(Span::new(start as u32, end as u32));
+        Ok(list.map(|mut list| {
+            for item in &mut list.items {
+                let new_text = item
+                    .insert_text
+                    .take()
+                    .unwrap_or_else(|| item.label.clone());
+                item.text_edit = Some(CompletionTextEdit::Edit(TextEdit { range, new_text }));
+            }
+            CompletionResponse::List(list)
+        }))
+    }
+
+    fn site(&self, offset: u32) -> Site {
+        let text = self.document.text.as_str();
+        // inside a string, what matters is what comes before it
+        let start = self
+            .string_at(offset)
+            .map_or(offset, |token| token.span.start);
+        let before = text[..start as usize].trim_end_matches(is_word_char);
+        let prev = before.trim_end();
+        match prev.chars().last() {
+            Some(':') => return Site::Type(prev.len() as u32 - 1),
+            Some('=') => return Site::Value(prev.len() as u32 - 1),
+            _ => {}
+        }
+
+        // blocks without a class hold container items rather than fields
+        match self.open_block(offset) {
+            Some(block) if block.class.is_none() => Site::Item,
+            _ => Site::Field,
+        }
+    }
+
+    /// The string literal the cursor is in or right before, if any.
+    fn string_at(&self, offset: u32) -> Option<&Token> {
+        self.tokens
+            .at(offset)
+            .map(|(token, _)| token)
+            .filter(|token| token.kind == TokenKind::String)
+    }
+
+    /// The innermost `{` before `offset` that hasn't been closed.
+    ///
+    /// This goes by the tokens alone rather than the tree, since the block being typed in is
+    /// often unfinished and ends up in an error tree.
+    pub(crate) fn open_block(&self, offset: u32) -> Option<OpenBlock> {
+        let tokens = &self.tokens.0;
+        let before = tokens.partition_point(|(t, _)| t.span.end <= offset);
+        let mut depth = 0;
+        for (i, (token, _)) in tokens[..before].iter().enumerate().rev() {
+            match token.kind {
+                TokenKind::RCurly => depth += 1,
+                TokenKind::LCurly if depth > 0 => depth -= 1,
+                TokenKind::LCurly => {
+                    let class = tokens[..i]
+                        .iter()
+                        .rev()
+                        .find(|(t, _)| t.kind != TokenKind::Comment)
+                        .filter(|(t, _)| matches!(t.kind, TokenKind::Name | TokenKind::HexLit))
+                        .map(|(t, _)| t.span);
+                    return Some(OpenBlock {
+                        brace: token.span,
+                        class,
+                    });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The `=` that assigns the block opened by `brace`, if it is a value rather than a
+    /// `Class { .. }` or a container item.
+    pub(crate) fn block_eq(&self, brace: Span) -> Option<u32> {
+        let before = self.document.text[..brace.start as usize].trim_end();
+        before.ends_with('=').then(|| before.len() as u32 - 1)
+    }
+
+    /// The entry whose `key: type = ` ends with the `=` at `eq`, if it parsed.
+    fn entry_at_eq(&self, eq: u32) -> Option<(usize, &Entry)> {
+        self.entries
+            .at(eq)
+            .filter(|(_, e)| e.key.end <= eq && e.value.is_none_or(|v| v.start >= eq))
+    }
+
+    /// Names of the fields of the enclosing class that aren't set yet, including inherited
+    /// ones, ranked by how well they match `prefix`.
+    ///
+    /// Documentation and snippets are left for `completionItem/resolve` when the client can
+    /// resolve them, since classes can have hundreds of fields.
+    fn field_completions(
+        &self,
+        offset: u32,
+        prefix: &str,
+        position: Position,
+        classes: &Classes,
+    ) -> Option<Vec<CompletionItem>> {
+        let text = self.document.text.as_str();
+        let resolvable = self
+            .server
+            .config
+            .caps()
+            .completion_resolve_support_properties();
+        let lazy_docs = resolvable.contains("documentation");
+        let lazy_snippet = resolvable.contains("textEdit");
+        let block = self.open_block(offset)?;
+        let class_hash = hash_name(&text[block.class?]);
+
+        // the field being typed doesn't count as set
+        let set = self
+            .entries
+            .blocks
+            .iter()
+            .position(|b| b.span.start == block.brace.start)
+            .into_iter()
+            .flat_map(|idx| self.entries.fields_in(idx))
+            .filter(|e| !(e.key.start <= offset && offset <= e.key.end))
+            .filter_map(|e| e.key_hash(text))
+            .collect::<FxHashSet<_>>();
+
+        let mut items = Vec::new();
+        let mut seen = FxHashSet::default();
+        for (hash, class) in classes.ancestors(class_hash) {
+            let inherited_from = (*hash != class_hash).then(|| self.type_name(*hash));
+            for (k, prop) in &class.properties {
+                if set.contains(&**k) || !seen.insert(*k) {
+                    continue;
+                }
+                let label = self
+                    .unhashed(HashKind::Field, **k)
+                    .map_or_else(|| format!("0x{:08x}", **k), |name| name.to_owned());
+                // clients filter the list themselves as more is typed, so every field is sent
+                // and the score only orders them
+                let score = fuzzy_score(prefix, &label).unwrap_or(99_999);
+                let type_part = format!(": {}", prop.rito_type());
+                let snippet = match lazy_snippet {
+                    true => None,
+                    false => self.field_snippet(&label, class_hash, **k, classes),
+                };
+                let data = (lazy_docs || lazy_snippet).then(|| {
+                    serde_json::to_value(CompletionResolveData {
+                        position: TextDocumentPositionParams::new(
+                            TextDocumentIdentifier::new(self.document.uri.clone()),
+                            position,
+                        ),
+                        class: class_hash,
+                        field: **k,
+                    })
+                    .ok()
+                });
+                items.push(CompletionItem {
+                    sort_text: Some(format!("{score:05}{label}")),
+                    filter_text: Some(label.clone()),
+                    insert_text_format: snippet.as_ref().map(|_| InsertTextFormat::SNIPPET),
+                    insert_text: Some(snippet.unwrap_or_else(|| format!("{label}{type_part} = "))),
+                    documentation: match lazy_docs {
+                        true => None,
+                        false => self.field_documentation(class_hash, **k, &label, classes),
+                    },
+                    label,
+                    label_details: Some(lsp_types::CompletionItemLabelDetails {
+                        detail: Some(type_part),
+                        description: inherited_from.as_ref().map(|base| format!("from {base}")),
+                    }),
+                    kind: Some(CompletionItemKind::PROPERTY),
+                    data: data.flatten(),
+                    ..Default::default()
+                });
+            }
+        }
+        Some(items)
+    }
+
+    /// Fill in what [`Worker::field_completions`] left out of a field completion.
+    pub fn resolve_completion(&self, mut item: CompletionItem) -> CompletionItem {
+        let Some(data) = item
+            .data
+            .take()
+            .and_then(|d| serde_json::from_value::<CompletionResolveData>(d).ok())
+        else {
+            return item;
+        };
+        let classes = self.server.meta.classes.read();
+
+        if item.documentation.is_none() {
+            item.documentation =
+                self.field_documentation(data.class, data.field, &item.label, &classes);
+        }
+        if item.insert_text_format != Some(InsertTextFormat::SNIPPET)
+            && let Some(snippet) = self.field_snippet(&item.label, data.class, data.field, &classes)
+            && let Some(CompletionTextEdit::Edit(edit)) = &mut item.text_edit
+        {
+            edit.new_text = snippet;
+            item.insert_text_format = Some(InsertTextFormat::SNIPPET);
+        }
+        item
+    }
+
+    /// The schema details of a field, as shown when hovering it.
+    fn field_documentation(
+        &self,
+        class: u32,
+        field: u32,
+        label: &str,
+        classes: &Classes,
+    ) -> Option<Documentation> {
+        let (declaring, prop) = classes.find_declaring(class, field)?;
+        let value = self.property_hover(
+            (&self.type_name(class), U32Hash(class)),
+            (label, U32Hash(field)),
+            declaring,
+            prop,
+            classes,
+        );
+        Some(markdown(&value))
+    }
+
+    /// `name: type = value`, with a tab stop on the default value, or a block to fill in for
+    /// classes and containers.
+    fn field_snippet(
+        &self,
+        label: &str,
+        class: u32,
+        field: u32,
+        classes: &Classes,
+    ) -> Option<String> {
+        if !self.server.config.caps().completion_snippet() {
+            return None;
+        }
+        let prop = classes.find_property(class, field)?;
+        let value = match (prop.value_type, prop.other_class) {
+            (BinType::Pointer | BinType::Embed, Some(other)) => {
+                format!("{} {{\n\t$0\n}}", self.type_name(*other))
+            }
+            (BinType::List | BinType::List2 | BinType::Map, _) => "{\n\t$0\n}".into(),
+            _ => {
+                let default = prop.default_literal(classes.find_default(class, field), |class| {
+                    self.type_name(*class)
+                });
+                format!("${{1:{}}}", escape_snippet(&default))
+            }
+        };
+        Some(format!(
+            "{}: {} = {value}",
+            escape_snippet(label),
+            prop.rito_type()
+        ))
+    }
+
+    /// Builtin type names, with the field's type from the schema first when we know it.
+    fn type_completions(&self, colon: u32, classes: &Classes) -> Vec<CompletionItem> {
+        let text = self.document.text.as_str();
+        let key = text[..colon as usize].trim_end();
+        let key = &key[key.trim_end_matches(|c| is_word_char(c) || c == '"').len()..];
+
+        // the entry may not have parsed yet, so go by the enclosing class block
+        let class = self.open_block(colon).and_then(|b| b.class);
+        let expected = class
+            .filter(|_| !key.is_empty())
+            .and_then(|class| classes.find_property(hash_name(&text[class]), hash_name(key)))
+            .map(|prop| prop.rito_type().to_string());
+
+        let snippets = self.server.config.caps().completion_snippet();
+        let mut items = expected
+            .iter()
+            .map(|expected| CompletionItem {
+                label: expected.clone(),
+                kind: Some(CompletionItemKind::TYPE_PARAMETER),
+                detail: Some("Schema type".into()),
+                preselect: Some(true),
+                sort_text: Some("0".into()),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        // an exact match of the schema type is covered by the item above
+        let builtins = TYPE_NAMES
+            .iter()
+            .filter(|name| expected.as_deref() != Some(**name));
+        items.extend(builtins.filter_map(|name| {
+            let ty = BinType::from_rito_name(name)?;
+            let (insert_text, insert_text_format) = match (ty, snippets) {
+                (BinType::Map, true) => (
+                    format!("{name}[${{1:hash}}, ${{2:string}}]"),
+                    InsertTextFormat::SNIPPET,
+                ),
+                (BinType::List | BinType::List2 | BinType::Option, true) => {
+                    (format!("{name}[${{1:string}}]"), InsertTextFormat::SNIPPET)
+                }
+                _ => (name.to_string(), InsertTextFormat::PLAIN_TEXT),
+            };
+            Some(CompletionItem {
+                label: name.to_string(),
+                kind: Some(CompletionItemKind::TYPE_PARAMETER),
+                detail: Some(ty.description().into()),
+                insert_text: Some(insert_text),
+                insert_text_format: Some(insert_text_format),
+                sort_text: Some(format!("1{name}")),
+                ..Default::default()
+            })
+        }));
+        items
+    }
+
+    /// Values that fit the field being assigned at `offset`, or its container items if `item`.
+    ///
+    /// `prefix` is what has been typed of the value so far.
+    fn value_completions(
+        &self,
+        offset: u32,
+        item: bool,
+        prefix: &str,
+        classes: &Classes,
+    ) -> Option<CompletionList> {
+        // container items are typed by the field whose `= {` opened their block
+        let eq = match item {
+            false => offset,
+            true => self.block_eq(self.open_block(offset)?.brace)?,
+        };
+        let (ty, other_class) = self.expected_value(eq, item, classes)?;
+
+        let default = match item {
+            true => None,
+            false => self.default_value(eq, classes),
+        };
+        let mut items = default
+            .iter()
+            .map(|default| CompletionItem {
+                label: default.clone(),
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some("Default value".into()),
+                preselect: Some(true),
+                sort_text: Some("0".into()),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut values = match ty {
+            BinType::Pointer | BinType::Embed => self.class_completions(other_class, classes),
+            BinType::Bool | BinType::Flag => ["true", "false"]
+                .into_iter()
+                .map(|value| value_item(value.into(), value.into(), CompletionItemKind::KEYWORD))
+                .collect(),
+            BinType::Link => self.link_completions(),
+            BinType::Hash => self
+                .server
+                .hashes
+                .hashes
+                .iter()
+                .flat_map(|table| table.hashes.values())
+                .filter(|name| matches_prefix(prefix, name))
+                .take(MAX_ITEMS + 1)
+                .map(|name| value_item(name.clone(), quote(name), CompletionItemKind::VALUE))
+                .collect(),
+            BinType::File => self.path_completions(prefix),
+            // plain strings are only sometimes paths
+            BinType::String if prefix.contains('/') => self.path_completions(prefix),
+            _ => Vec::new(),
+        };
+        // hash tables and WAD listings are too big to send in full
+        let limited = matches!(ty, BinType::Hash | BinType::File | BinType::String);
+        let is_incomplete = limited && values.len() > MAX_ITEMS;
+        if is_incomplete {
+            values.truncate(MAX_ITEMS);
+        }
+
+        // the default is already offered first
+        values.retain(|v| Some(&v.label) != default.as_ref());
+        items.extend(values);
+        match items.is_empty() {
+            true => None,
+            false => Some(CompletionList {
+                is_incomplete,
+                items,
+            }),
+        }
+    }
+
+    /// The default value of the field assigned by the `=` at `eq`, from the class schema.
+    fn default_value(&self, eq: u32, classes: &Classes) -> Option<String> {
+        let (_, entry) = self.entry_at_eq(eq).filter(|(_, e)| e.is_field())?;
+        let class = self.entry_class(entry)?;
+        let field = entry.key_hash(&self.document.text)?;
+        let prop = classes.find_property(class, field)?;
+        Some(
+            prop.default_literal(classes.find_default(class, field), |class| {
+                self.unhashed(HashKind::Type, *class)
+                    .map_or_else(|| class.to_string(), |name| name.to_owned())
+            }),
+        )
+    }
+
+    /// Bin objects from this document and the rest of the workspace.
+    fn link_completions(&self) -> Vec<CompletionItem> {
+        let text = self.document.text.as_str();
+        let mut objects = self.server.workspace.objects(&self.document.uri);
+        for (_, entry) in self.entries.objects(text) {
+            let Some(hash) = entry.key_hash(text) else {
+                continue;
+            };
+            let name = (entry.key_kind == TokenKind::String).then(|| unquote(&text[entry.key]));
+            objects.insert(hash, name.map(str::to_owned));
+        }
+
+        objects
+            .into_iter()
+            .map(|(hash, name)| match name {
+                Some(name) => {
+                    let quoted = quote(&name);
+                    value_item(name, quoted, CompletionItemKind::REFERENCE)
+                }
+                None => match self.unhashed(HashKind::Entry, hash) {
+                    Some(name) => {
+                        value_item(name.to_owned(), quote(name), CompletionItemKind::REFERENCE)
+                    }
+                    None => {
+                        let hex = format!("0x{hash:08x}");
+                        value_item(hex.clone(), hex, CompletionItemKind::REFERENCE)
+                    }
+                },
+            })
+            .collect()
+    }
+
+    /// Asset paths written anywhere in the workspace, and from the WAD listing.
+    fn path_completions(&self, prefix: &str) -> Vec<CompletionItem> {
+        let text = self.document.text.as_str();
+        let mut paths = self.server.workspace.asset_paths(&self.document.uri);
+        paths.extend(
+            self.tokens
+                .iter()
+                .filter(|(t, _)| t.kind == TokenKind::String)
+                .map(|(t, _)| unquote(&text[t.span]))
+                .filter(|s| is_asset_path(s) && *s != unquote(prefix))
+                .map(str::to_owned),
+        );
+
+        // the listing is lowercased up front, since it can hold hundreds of thousands of paths
+        let needle = prefix.trim_start_matches(['"', '\'']).to_ascii_lowercase();
+        let wad = self
+            .server
+            .wad_paths
+            .iter()
+            .filter(|p| p.lower.contains(&needle) && !paths.contains(&p.path))
+            .map(|p| &p.path);
+        paths
+            .iter()
+            .filter(|path| matches_prefix(prefix, path))
+            .chain(wad)
+            .take(MAX_ITEMS + 1)
+            .map(|path| value_item(path.clone(), quote(path), CompletionItemKind::FILE))
+            .collect()
+    }
+
+    /// The type of the value assigned by the `=` at `eq` (or of its container items), with the
+    /// class it should be an instance of when the schema says.
+    fn expected_value(
+        &self,
+        eq: u32,
+        item: bool,
+        classes: &Classes,
+    ) -> Option<(BinType, Option<U32Hash>)> {
+        let text = self.document.text.as_str();
+        let Some((idx, entry)) = self.entry_at_eq(eq) else {
+            return self.expected_value_from_text(eq, item, classes);
+        };
+        let (field, ty) = match (item, entry.is_field()) {
+            (false, true) => {
+                let ty = match self.property(entry, classes) {
+                    Some(prop) => prop.value_type,
+                    None => BinType::from_rito_name(TypeExpr::parse(&text[entry.type_expr?]).base)?,
+                };
+                (idx, ty)
+            }
+            (true, true) => (idx, self.element_type(idx, classes, false)?),
+            // map values are typed by the map
+            (false, false) => {
+                let parent = entry.parent?;
+                (parent, self.element_type(parent, classes, false)?)
+            }
+            (true, false) => return None,
+        };
+        let other_class = self
+            .property(&self.entries.entries[field], classes)
+            .and_then(|p| p.other_class);
+        Some((ty, other_class))
+    }
+
+    /// Like [`Worker::expected_value`], reading `key: type =` straight from the text for entries
+    /// that haven't parsed.
+    fn expected_value_from_text(
+        &self,
+        eq: u32,
+        item: bool,
+        classes: &Classes,
+    ) -> Option<(BinType, Option<U32Hash>)> {
+        let (_, ty, prop) = self.field_header(eq, classes)?;
+        let ty = TypeExpr::parse(ty);
+        let ty = match (prop, item) {
+            (Some(prop), false) => prop.value_type,
+            (Some(prop), true) => match (&prop.map, &prop.container) {
+                (Some(map), _) => map.value_type,
+                (None, Some(container)) => container.value_type,
+                (None, None) => return None,
+            },
+            (None, false) => BinType::from_rito_name(ty.base)?,
+            (None, true) => BinType::from_rito_name(ty.value_type())?,
+        };
+        Some((ty, prop.and_then(|p| p.other_class)))
+    }
+
+    /// The key and type of the `key: type =` ending at `eq`, read from the text, with the
+    /// schema property when the enclosing class has a field of that name.
+    pub(crate) fn field_header<'c>(
+        &self,
+        eq: u32,
+        classes: &'c Classes,
+    ) -> Option<(&str, &str, Option<&'c Property>)> {
+        let text = self.document.text.as_str();
+        let header = &text[..eq as usize];
+        let header = &header[header.rfind(['\n', '{']).map_or(0, |i| i + 1)..];
+        let (key, ty) = header.split_once(':')?;
+        let key = key.trim();
+
+        let class = self.open_block(eq).and_then(|b| b.class);
+        let prop =
+            class.and_then(|class| classes.find_property(hash_name(&text[class]), hash_name(key)));
+        Some((key, ty.trim(), prop))
+    }
+
+    /// Classes that can be instantiated where `other_class` is expected, or any class.
+    fn class_completions(
+        &self,
+        other_class: Option<U32Hash>,
+        classes: &Classes,
+    ) -> Vec<CompletionItem> {
+        let candidates = match other_class {
+            Some(class) => classes.subclasses(class).into_iter().collect(),
+            None => classes.iter().map(|(hash, _)| hash).collect::<Vec<_>>(),
+        };
+        let snippets = self.server.config.caps().completion_snippet();
+
+        candidates
+            .into_iter()
+            .filter(|hash| classes.get(*hash).is_some_and(|c| !c.is.interface))
+            .map(|hash| {
+                let name = self
+                    .unhashed(HashKind::Type, *hash)
+                    .map_or_else(|| format!("0x{:08x}", *hash), |name| name.to_owned());
+                let (insert_text, insert_text_format) = match snippets {
+                    true => (format!("{name} {{\n\t$0\n}}"), InsertTextFormat::SNIPPET),
+                    false => (format!("{name} {{}}"), InsertTextFormat::PLAIN_TEXT),
+                };
+                CompletionItem {
+                    label: name,
+                    kind: Some(CompletionItemKind::CLASS),
+                    insert_text: Some(insert_text),
+                    insert_text_format: Some(insert_text_format),
+                    documentation: self.server.docs.class(*hash).map(markdown),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Most items offered from large sources like hash tables, after which the list is marked as
+/// incomplete so the client asks again as more is typed.
+const MAX_ITEMS: usize = 200;
+
+/// The builtin types, as named in type expressions.
+const TYPE_NAMES: &[&str] = &[
+    "bool", "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "f32", "vec2", "vec3", "vec4",
+    "mtx44", "rgba", "string", "hash", "file", "link", "flag", "list", "list2", "option", "map",
+    "pointer", "embed",
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `candidate` could be what's being typed, ignoring quotes and case.
+fn matches_prefix(prefix: &str, candidate: &str) -> bool {
+    let prefix = prefix.trim_start_matches(['"', '\'']);
+    candidate
+        .to_ascii_lowercase()
+        .contains(&prefix.to_ascii_lowercase())
+}
+
+fn value_item(label: String, insert_text: String, kind: CompletionItemKind) -> CompletionItem {
+    CompletionItem {
+        filter_text: Some(insert_text.clone()),
+        label,
+        kind: Some(kind),
+        insert_text: Some(insert_text),
+        ..Default::default()
+    }
+}
+
+/// How well `candidate` matches the typed `pattern`, lower is better.
+///
+/// Prefix matches come first, then substrings, then candidates that contain the pattern's
+/// characters in order. `None` if it doesn't match at all.
+fn fuzzy_score(pattern: &str, candidate: &str) -> Option<u32> {
+    let pattern = pattern.to_ascii_lowercase();
+    let candidate = candidate.to_ascii_lowercase();
+    if candidate.starts_with(&pattern) {
+        return Some(0);
+    }
+    if let Some(pos) = candidate.find(&pattern) {
+        return Some(100 + pos as u32);
+    }
+
+    let mut chars = candidate.char_indices();
+    let mut last = None;
+    let mut gaps = 0;
+    for p in pattern.chars() {
+        let (i, _) = chars.by_ref().find(|(_, c)| *c == p)?;
+        if last.is_some_and(|last| i != last + 1) {
+            gaps += 1;
+        }
+        last = Some(i);
+    }
+    Some(1000 + gaps * 10)
+}
+
+pub(crate) fn markdown(value: &str) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: value.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_server::RequestId;
+    use serde_json::json;
+
+    use super::*;
+    use crate::worker::fixture::Fixture;
+
+    /// The worker for `text` with the cursor at its `$0`, and the completions there.
+    fn complete(fixture: Fixture, text: &str) -> (Worker, Vec<CompletionItem>) {
+        let offset = text.find("$0").expect("no cursor") as u32;
+        let worker = fixture.worker(&text.replace("$0", ""));
+        let req = CompletionRequest {
+            id: RequestId::from(0),
+            position: worker.document.line_numbers.position(offset),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let items = match worker.complete(req).unwrap() {
+            Some(CompletionResponse::List(list)) => list.items,
+            _ => Vec::new(),
+        };
+        (worker, items)
+    }
+
+    #[test]
+    fn fuzzy_ranking() {
+        let score = |candidate| fuzzy_score("mmat", candidate);
+        assert!(score("mMaterial") < score("baseMMaterial"));
+        assert!(score("baseMMaterial") < score("mMeshAttachment"));
+        assert_eq!(score("mName"), None);
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn classes_for_pointer_values() {
+        let fixture = Fixture::default()
+            .class("Shape", None, &[])
+            .set("Shape", "is/interface", json!(true))
+            .class("Circle", Some("Shape"), &[])
+            .class("Square", Some("Shape"), &[])
+            .class("Unrelated", None, &[])
+            .class("Holder", None, &[("mShape", "pointer")])
+            .other_class("Holder", "mShape", "Shape");
+        let (_, items) = complete(
+            fixture,
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mShape: pointer = $0
+    }
+}
+"#,
+        );
+        let mut classes = items
+            .iter()
+            .filter(|item| item.kind == Some(CompletionItemKind::CLASS))
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        classes.sort();
+        assert_eq!(classes, ["Circle", "Square"]);
+    }
+
+    #[test]
+    fn schema_type_first() {
+        let fixture = Fixture::default().class("Holder", None, &[("mTags", "list[string]")]);
+        let (_, items) = complete(
+            fixture,
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mTags: $0
+    }
+}
+"#,
+        );
+        assert_eq!(items[0].label, "list[string]");
+        assert_eq!(items[0].preselect, Some(true));
+        let labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert!(labels.contains(&"list"));
+        assert_eq!(labels.iter().filter(|l| **l == "list[string]").count(), 1);
+    }
+
+    #[test]
+    fn bool_and_link_values() {
+        let fixture = || {
+            Fixture::default()
+                .class("Holder", None, &[("mEnabled", "bool"), ("mLink", "link")])
+                .field_default("Holder", "mEnabled", json!(false))
+        };
+
+        // the default comes first, and isn't offered twice
+        let (_, items) = complete(
+            fixture(),
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mEnabled: bool = $0true
+    }
+}
+"#,
+        );
+        let labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["false", "true"]);
+        assert_eq!(items[0].detail.as_deref(), Some("Default value"));
+
+        let (_, items) = complete(
+            fixture(),
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mLink: link = $0""
+    }
+    "Characters/Other" = Holder {}
+}
+"#,
+        );
+        let mut links = items
+            .iter()
+            .filter(|item| item.kind == Some(CompletionItemKind::REFERENCE))
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        links.sort();
+        assert_eq!(links, ["Characters/Other", "Characters/Test"]);
+
+        // the whole string is replaced, including a closing quote inserted by the editor
+        let (worker, items) = complete(
+            fixture(),
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mLink: link = "Characters/O$0"
+    }
+    "Characters/Other" = Holder {}
+}
+"#,
+        );
+        let item = items
+            .iter()
+            .find(|item| item.label == "Characters/Other")
+            .unwrap();
+        let Some(CompletionTextEdit::Edit(edit)) = &item.text_edit else {
+            panic!("expected a text edit: {item:?}");
+        };
+        let replaced = worker.document.line_numbers.from_range(&edit.range);
+        assert_eq!(&worker.document.text.as_str()[replaced], "\"Characters/O\"");
+        assert_eq!(edit.new_text, "\"Characters/Other\"");
+    }
+
+    #[test]
+    fn resolve_field_docs_and_snippet() {
+        let fixture = Fixture::default()
+            .class("Holder", None, &[("mEnabled", "bool")])
+            .field_default("Holder", "mEnabled", json!(false))
+            .capabilities(json!({
+                "textDocument": {
+                    "completion": {
+                        "completionItem": {
+                            "snippetSupport": true,
+                            "resolveSupport": { "properties": ["documentation", "textEdit"] },
+                        },
+                    },
+                },
+            }));
+        let (worker, items) = complete(
+            fixture,
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mEn$0
+    }
+}
+"#,
+        );
+        let [item] = &items[..] else {
+            panic!("expected one field: {items:?}");
+        };
+        assert!(item.documentation.is_none());
+        assert!(item.data.is_some());
+
+        let item = worker.resolve_completion(item.clone());
+        assert!(item.documentation.is_some());
+        assert_eq!(item.insert_text_format, Some(InsertTextFormat::SNIPPET));
+        let Some(CompletionTextEdit::Edit(edit)) = &item.text_edit else {
+            panic!("no text edit: {item:?}");
+        };
+        assert_eq!(edit.new_text, "mEnabled: bool = ${1:false}");
+    }
+
+    #[test]
+    fn complete_in_broken_blocks() {
+        let fixture = || Fixture::default().class("Holder", None, &[("mEnabled", "bool")]);
+
+        let text = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mEn$0
+"#;
+        let (worker, items) = complete(fixture(), text);
+        let offset = text.find("$0").unwrap() as u32;
+        assert_eq!(worker.site(offset), Site::Field);
+        let labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["mEnabled"]);
+
+        let text = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mEnabled: bool = $0
+        other: string = ""
+    }
+}
+"#;
+        let (worker, items) = complete(fixture(), text);
+        let offset = text.find("$0").unwrap() as u32;
+        assert_eq!(worker.site(offset), Site::Value(offset - 2));
+        let mut labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, ["false", "true"]);
+    }
+
+    #[test]
+    fn fields_not_matching_the_prefix_sort_last() {
+        let fixture = Fixture::default().class(
+            "Holder",
+            None,
+            &[
+                ("mEnabled", "bool"),
+                ("mName", "string"),
+                ("mBaseEnabled", "bool"),
+            ],
+        );
+        let (_, mut items) = complete(
+            fixture,
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mEn$0
+    }
+}
+"#,
+        );
+        items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+        let labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["mEnabled", "mBaseEnabled", "mName"]);
+    }
+}
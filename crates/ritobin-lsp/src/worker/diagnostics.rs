@@ -1,107 +1,128 @@
+use std::hash::{Hash as _, Hasher as _};
+
 use itertools::Itertools as _;
 use lsp_types::{
-    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
-    PublishDiagnosticsParams,
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DocumentDiagnosticReport,
+    FullDocumentDiagnosticReport, Location, NumberOrString, PublishDiagnosticsParams,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport,
     notification::{Notification as _, PublishDiagnostics},
 };
-use ltk_ritobin::{Cst, cst::FlatErrors, parse::ErrorKind, typecheck::visitor::DiagnosticWithSpan};
+use ltk_ritobin::{
+    Cst,
+    cst::{FlatErrors, Kind as TreeKind},
+    parse::{ErrorKind, Span, Token},
+    typecheck::visitor::{self, DiagnosticWithSpan},
+};
+use ritobin_lsp::syntax::Entries;
+use rustc_hash::FxHasher;
 
-use crate::worker::Worker;
+use crate::{document::Document, server::Server, worker::Worker};
+
+/// Builds the diagnostics for a single document, shared between open documents (workers) and
+/// workspace files that are only indexed from disk.
+pub struct DiagnosticCollector<'a> {
+    pub server: &'a Server,
+    pub document: &'a Document,
+}
+
+impl DiagnosticCollector<'_> {
+    pub(crate) fn related(
+        &self,
+        span: Span,
+        message: impl Into<String>,
+    ) -> DiagnosticRelatedInformation {
+        DiagnosticRelatedInformation {
+            location: Location {
+                uri: self.document.uri.clone(),
+                range: self.document.line_numbers.from_span(span),
+            },
+            message: message.into(),
+        }
+    }
 
-impl Worker {
     fn convert_diagnostic(&self, d: DiagnosticWithSpan) -> Diagnostic {
-        match d.diagnostic {
-            ltk_ritobin::typecheck::visitor::Diagnostic::TypeMismatch {
+        use visitor::Diagnostic::*;
+
+        let text = self.document.text.as_str();
+        let (span, related_information) = match &d.diagnostic {
+            TypeMismatch {
                 span,
-                expected,
                 expected_span,
-                got,
-            } => Diagnostic {
-                range: self.document.line_numbers.from_span(span),
-                severity: Some(DiagnosticSeverity::ERROR),
-                related_information: expected_span.map(|span| {
-                    vec![DiagnosticRelatedInformation {
-                        location: Location {
-                            uri: self.document.uri.clone(),
-                            range: self.document.line_numbers.from_span(span),
-                        },
-                        message: "due to this type expression".into(),
-                    }]
-                }),
-                message: format!("Type mismatch - expected {expected}, got {got}"),
-                ..Default::default()
-            },
-            ltk_ritobin::typecheck::visitor::Diagnostic::ShadowedEntry { shadowee, shadower } => {
-                Diagnostic {
-                    range: self.document.line_numbers.from_span(d.span),
-                    severity: Some(DiagnosticSeverity::WARNING),
-
-                    related_information: Some(vec![DiagnosticRelatedInformation {
-                        location: Location {
-                            uri: self.document.uri.clone(),
-                            range: self.document.line_numbers.from_span(shadowee),
-                        },
-                        message: "Shadowed here".into(),
-                    }]),
-
-                    message: format!(
-                        "Entry '{}' shadows previous entry",
-                        &self.document.text.as_str()[shadower]
-                    ),
-                    ..Default::default()
-                }
+                ..
             }
-            ltk_ritobin::typecheck::visitor::Diagnostic::RootNonEntry => Diagnostic {
-                range: self.document.line_numbers.from_span(d.span),
-                severity: Some(DiagnosticSeverity::ERROR),
-                message: "Top-level bin entries must be of form 'name: type = ..'".into(),
-                ..Default::default()
-            },
-            ltk_ritobin::typecheck::visitor::Diagnostic::UnexpectedSubtypes {
-                base_type, ..
-            } => Diagnostic {
-                range: self.document.line_numbers.from_span(d.span),
-                severity: Some(DiagnosticSeverity::ERROR),
-                message: format!(
-                    "{} does not accept type parameters",
-                    &self.document.text.as_str()[base_type]
-                ),
-                ..Default::default()
-            },
-            ltk_ritobin::typecheck::visitor::Diagnostic::UnexpectedContainerItem {
+            | UnexpectedContainerItem {
                 span,
-                expected,
-                expected_span: _,
-            } => {
-                let mut expected = expected.to_string();
-                make_ascii_titlecase(&mut expected);
-                Diagnostic {
-                    range: self.document.line_numbers.from_span(span),
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    message: format!(
-                        "{expected} type does not accept container items / blocks!\nRemove any curly braces surrounding the value."
-                    ),
-                    ..Default::default()
-                }
+                expected_span,
+                ..
+            } => (
+                *span,
+                expected_span.map(|s| vec![self.related(s, "due to this type expression")]),
+            ),
+            ShadowedEntry { shadowee, shadower } => (
+                *shadower,
+                Some(vec![self.related(
+                    *shadowee,
+                    format!("'{}' first defined here", &text[*shadowee]),
+                )]),
+            ),
+            UnexpectedSubtypes { span, base_type } => {
+                (*span, Some(vec![self.related(*base_type, "base type")]))
             }
-            inner => Diagnostic {
-                range: self.document.line_numbers.from_span(d.span),
+            UnknownType(span) | MissingType(span) | AmbiguousNumeric(span) | InvalidHash(span) => {
+                (*span, None)
+            }
+            NotEnoughItems { span, .. }
+            | TooManyItems { span, .. }
+            | SubtypeCountMismatch { span, .. } => (*span, None),
+            MissingTree(_) | EmptyTree(_) | MissingToken(_) | ResolveLiteral | RootNonEntry => {
+                (d.span, None)
+            }
+        };
 
-                severity: Some(DiagnosticSeverity::ERROR),
-                message: format!("{inner:?}"),
-                ..Default::default()
-            },
+        Diagnostic {
+            range: self.document.line_numbers.from_span(span),
+            severity: Some(match d.diagnostic {
+                ShadowedEntry { .. } => DiagnosticSeverity::WARNING,
+                _ => DiagnosticSeverity::ERROR,
+            }),
+            code: Some(NumberOrString::String(
+                diagnostic_code(&d.diagnostic).to_owned(),
+            )),
+            message: diagnostic_message(text, &d.diagnostic),
+            related_information,
+            ..Default::default()
         }
     }
 
-    pub fn publish_parse_errors(
+    pub fn collect(
         &self,
         cst: &Cst,
+        entries: &Entries,
+        unresolved_links: &[&Token],
         bin_errors: impl IntoIterator<Item = DiagnosticWithSpan>,
-    ) -> anyhow::Result<()> {
-        let mut diagnostics = bin_errors
+    ) -> Vec<Diagnostic> {
+        let bin_errors = bin_errors
             .into_iter()
             .map(|d| self.convert_diagnostic(d))
+            .collect_vec();
+        // bin objects set twice may already be reported as shadowed entries
+        let shadowed = bin_errors
+            .iter()
+            .filter(|d| d.code == Some(NumberOrString::String("shadowed-entry".into())))
+            .map(|d| d.range)
+            .collect_vec();
+        let duplicates = self
+            .duplicate_keys(entries)
+            .into_iter()
+            .filter(|d| !shadowed.contains(&d.range));
+        let mut type_errors = bin_errors
+            .into_iter()
+            .chain(duplicates)
+            .chain(self.unresolved_link_diagnostics(unresolved_links))
+            .chain(self.schema_fields(entries))
+            .chain(self.default_values(entries))
+            .chain(self.missing_fields(entries))
             .update(|d| {
                 d.source.replace("ritobin-lsp".into());
             })
@@ -111,31 +132,41 @@ impl Worker {
         cst.walk(&mut parse_errors);
         let parse_errors = parse_errors.into_errors();
 
-        diagnostics.extend(parse_errors.into_iter().map(|err| Diagnostic {
-            range: self.document.line_numbers.from_span(err.span),
-            severity: Some(DiagnosticSeverity::ERROR),
-            code: None,
-            code_description: None,
-            source: Some("ritobin-lsp".into()),
-            message: match err.kind {
-                ErrorKind::Expected { expected, got } => {
-                    format!("Missing {expected} for {} - got {got}", err.tree)
-                }
-                ErrorKind::Unexpected { token } => {
-                    format!("Unexpected {token}, expected {}", err.tree)
-                }
-                kind => format!("{kind:#?}"),
-            },
-            related_information: None,
-            tags: None,
-            data: None,
-        }));
+        let mut diagnostics = parse_errors
+            .into_iter()
+            .map(|err| Diagnostic {
+                range: self.document.line_numbers.from_span(err.span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some("ritobin-lsp".into()),
+                message: parse_error_message(&err.kind, err.tree),
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect_vec();
+
+        // type errors are usually a consequence of parse errors, so when we can't report
+        // everything, parse errors take priority
+        if let Some(max) = self.server.config.diagnostics_max_count() {
+            diagnostics.sort_by_key(|d| d.range.start);
+            diagnostics.truncate(max);
+            type_errors.sort_by_key(|d| d.range.start);
+            type_errors.truncate(max - diagnostics.len());
+        }
+        diagnostics.extend(type_errors);
+        diagnostics.sort_by_key(|d| d.range.start);
+        diagnostics
+    }
+}
 
-        diagnostics.truncate(20);
+impl Worker {
+    pub fn publish_diagnostics(&self) -> anyhow::Result<()> {
         let params = PublishDiagnosticsParams {
             uri: self.document.uri.clone(),
-            diagnostics,
-            version: None,
+            diagnostics: self.diagnostics.clone(),
+            version: Some(self.document.version),
         };
         self.server
             .conn
@@ -145,6 +176,132 @@ impl Worker {
             ))?;
         Ok(())
     }
+
+    pub fn document_diagnostic(
+        &self,
+        previous_result_id: Option<String>,
+    ) -> DocumentDiagnosticReport {
+        let result_id = result_id(&self.diagnostics);
+        if previous_result_id.is_some_and(|prev| prev == result_id) {
+            return DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id,
+                },
+            });
+        }
+
+        DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(result_id),
+                items: self.diagnostics.clone(),
+            },
+        })
+    }
+}
+
+/// The `resultId` of a set of diagnostics, for pull-model diagnostic requests.
+///
+/// This is derived from the diagnostics themselves rather than the document version, since
+/// diagnostics can change due to other files in the workspace.
+pub fn result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = FxHasher::default();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+/// Short stable identifier for a typecheck diagnostic, reported as the diagnostic's `code`.
+pub fn diagnostic_code(d: &visitor::Diagnostic) -> &'static str {
+    use visitor::Diagnostic::*;
+
+    match d {
+        MissingTree(_) => "missing-tree",
+        EmptyTree(_) => "empty-tree",
+        MissingToken(_) => "missing-token",
+        UnknownType(_) => "unknown-type",
+        MissingType(_) => "missing-type",
+        TypeMismatch { .. } => "type-mismatch",
+        ResolveLiteral => "unresolved-literal",
+        AmbiguousNumeric(_) => "ambiguous-numeric",
+        NotEnoughItems { .. } => "not-enough-items",
+        TooManyItems { .. } => "too-many-items",
+        ShadowedEntry { .. } => "shadowed-entry",
+        InvalidHash(_) => "invalid-hash",
+        SubtypeCountMismatch { .. } => "subtype-count-mismatch",
+        UnexpectedSubtypes { .. } => "unexpected-subtypes",
+        RootNonEntry => "root-non-entry",
+        UnexpectedContainerItem { .. } => "unexpected-container-item",
+    }
+}
+
+/// Human readable message for a typecheck diagnostic.
+///
+/// This deliberately has no wildcard arm, so new upstream variants have to be handled here.
+pub fn diagnostic_message(text: &str, d: &visitor::Diagnostic) -> String {
+    use visitor::Diagnostic::*;
+
+    match d {
+        MissingTree(kind) => format!("Missing {kind}"),
+        EmptyTree(kind) => format!("{kind} must not be empty"),
+        MissingToken(token) => format!("Missing {token}"),
+        UnknownType(span) => format!("Unknown type '{}'", &text[*span]),
+        MissingType(span) => format!(
+            "'{}' needs a type - expected 'name: type = ..'",
+            &text[*span]
+        ),
+        TypeMismatch { expected, got, .. } => {
+            format!("Type mismatch - expected {expected}, got {got}")
+        }
+        ResolveLiteral => "Could not resolve the value of this literal".into(),
+        AmbiguousNumeric(span) => format!(
+            "Ambiguous number '{}' - add a type expression to disambiguate",
+            &text[*span]
+        ),
+        NotEnoughItems { got, expected, .. } => {
+            format!("Not enough items - expected {expected}, got {got}")
+        }
+        TooManyItems {
+            extra, expected, ..
+        } => {
+            format!("Too many items - expected {expected}, got {extra} more")
+        }
+        ShadowedEntry { shadower, .. } => {
+            format!("Entry '{}' shadows previous entry", &text[*shadower])
+        }
+        InvalidHash(span) => format!(
+            "Invalid hash '{}' - expected a hex literal like 0x1234abcd",
+            &text[*span]
+        ),
+        SubtypeCountMismatch { got, expected, .. } => {
+            format!("Wrong number of type parameters - expected {expected}, got {got}")
+        }
+        UnexpectedSubtypes { base_type, .. } => {
+            format!("{} does not accept type parameters", &text[*base_type])
+        }
+        RootNonEntry => "Top-level bin entries must be of form 'name: type = ..'".into(),
+        UnexpectedContainerItem { expected, .. } => {
+            let mut expected = expected.to_string();
+            make_ascii_titlecase(&mut expected);
+            format!(
+                "{expected} type does not accept container items / blocks!\nRemove any curly braces surrounding the value."
+            )
+        }
+    }
+}
+
+/// Human readable message for a parse error inside a `tree`.
+pub fn parse_error_message(kind: &ErrorKind, tree: TreeKind) -> String {
+    match kind {
+        ErrorKind::Expected { expected, got } => {
+            format!("Missing {expected} for {tree} - got {got}")
+        }
+        ErrorKind::Unexpected { token } => format!("Unexpected {token}, expected {tree}"),
+        ErrorKind::UnterminatedString => "Unterminated string - missing closing quote".into(),
+        ErrorKind::Custom(msg) => format!("{msg} in {tree}"),
+    }
 }
 
 fn make_ascii_titlecase(s: &mut str) {
@@ -152,3 +309,73 @@ fn make_ascii_titlecase(s: &mut str) {
         r.make_ascii_uppercase();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::fixture::Fixture;
+
+    // `diagnostic_message` and `parse_error_message` match exhaustively, so this module stops
+    // compiling when upstream adds a variant we don't handle yet.
+    #[test]
+    fn messages_are_human_readable() {
+        for src in [
+            "a: u32 = \"str\"\n",
+            "a: u32 = 1\na: u32 = 2\n",
+            "a: vec3[u32] = { 1, 2, 3 }\n",
+            "a: vec3 = { 1, 2 }\n",
+            "a: vec2 = { 1, 2, 3 }\n",
+            "a: u32 = { 1 }\n",
+            "a: foo = 1\n",
+            "a: map[hash] = {}\n",
+            "a: hash = 0xzz\n",
+            "5\n",
+            "a: u32 = \n",
+            "a: string = \"unterminated\n",
+        ] {
+            let cst = Cst::parse(src);
+            let (_, errors) = cst.build_bin(src);
+            for d in errors {
+                let message = diagnostic_message(src, &d.diagnostic);
+                assert!(!message.is_empty(), "empty message for {src:?}");
+                assert!(
+                    !message.contains("Span"),
+                    "debug output for {src:?}: {message}"
+                );
+            }
+
+            let mut parse_errors = FlatErrors::new();
+            cst.walk(&mut parse_errors);
+            for err in parse_errors.into_errors() {
+                let message = parse_error_message(&err.kind, err.tree);
+                assert!(
+                    !message.contains("Span"),
+                    "debug output for {src:?}: {message}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unchanged_document_diagnostics() {
+        let worker = Fixture::default().worker("a: u32 = \"str\"\n");
+        let DocumentDiagnosticReport::Full(full) = worker.document_diagnostic(None) else {
+            panic!("expected a full report");
+        };
+        let report = full.full_document_diagnostic_report;
+        assert!(!report.items.is_empty());
+        let result_id = report.result_id.unwrap();
+
+        match worker.document_diagnostic(Some(result_id.clone())) {
+            DocumentDiagnosticReport::Unchanged(unchanged) => assert_eq!(
+                unchanged.unchanged_document_diagnostic_report.result_id,
+                result_id
+            ),
+            DocumentDiagnosticReport::Full(_) => panic!("expected an unchanged report"),
+        }
+        assert!(matches!(
+            worker.document_diagnostic(Some("stale".into())),
+            DocumentDiagnosticReport::Full(_)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use lsp_types::{DocumentLink, Url};
+use ltk_ritobin::{
+    cst::Kind as TreeKind,
+    parse::{Span, TokenKind},
+};
+use ritobin_lsp::syntax::{hash_name, is_asset_path, unquote};
+
+use crate::worker::{Worker, hover::wiki_url, unhash::HashKind};
+
+impl Worker {
+    /// Asset paths that exist under one of the asset roots, and class names that have a meta
+    /// wiki page.
+    pub fn document_links(&self) -> Vec<DocumentLink> {
+        let text = self.document.text.as_str();
+        let roots = self.server.config.asset_roots();
+        let classes = self.server.meta.classes.read();
+
+        let mut links = Vec::new();
+        for (token, tree) in self.tokens.iter() {
+            let (target, tooltip, span) = match (token.kind, tree) {
+                (TokenKind::String, _) => {
+                    let path = unquote(&text[token.span]);
+                    if !is_asset_path(path) {
+                        continue;
+                    }
+                    let Some(file) = self.resolve_asset(&roots, path) else {
+                        continue;
+                    };
+                    let Ok(target) = Url::from_file_path(&file) else {
+                        continue;
+                    };
+                    // the path without its quotes
+                    let span = Span::new(token.span.start + 1, token.span.end - 1);
+                    (target, format!("Open {}", file.display()), span)
+                }
+                (TokenKind::Name | TokenKind::HexLit, TreeKind::Class) => {
+                    let hash = hash_name(&text[token.span]);
+                    if classes.get(hash).is_none() {
+                        continue;
+                    }
+                    let name = match token.kind {
+                        TokenKind::HexLit => match self.unhashed(HashKind::Type, hash) {
+                            Some(name) => name,
+                            None => continue,
+                        },
+                        _ => &text[token.span],
+                    };
+                    let Ok(target) = Url::parse(&wiki_url(name)) else {
+                        continue;
+                    };
+                    (target, format!("{name} on the meta wiki"), token.span)
+                }
+                _ => continue,
+            };
+            links.push(DocumentLink {
+                range: self.document.line_numbers.from_span(span),
+                target: Some(target),
+                tooltip: Some(tooltip),
+                data: None,
+            });
+        }
+        links
+    }
+
+    /// [`find_asset`], remembered per path until files change on disk.
+    fn resolve_asset(&self, roots: &[PathBuf], path: &str) -> Option<PathBuf> {
+        if let Some(file) = self.server.asset_files.read().get(path) {
+            return file.clone();
+        }
+        let file = find_asset(roots, path);
+        self.server
+            .asset_files
+            .write()
+            .insert(path.to_owned(), file.clone());
+        file
+    }
+}
+
+/// The file `path` names under the first root that has it. Extracted WADs usually have
+/// lowercased paths, so that is tried too.
+fn find_asset(roots: &[PathBuf], path: &str) -> Option<PathBuf> {
+    let lower = path.to_ascii_lowercase();
+    let spellings = match lower == path {
+        true => &[path][..],
+        false => &[path, lower.as_str()][..],
+    };
+    roots
+        .iter()
+        .flat_map(|root| spellings.iter().map(move |path| root.join(path)))
+        .find(|file| file.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::worker::fixture::{Fixture, hex};
+
+    /// The text each link covers, with its target.
+    fn links(worker: &Worker) -> Vec<(&str, String)> {
+        let text = worker.document.text.as_str();
+        worker
+            .document_links()
+            .into_iter()
+            .map(|link| {
+                let span = worker.document.line_numbers.from_range(&link.range);
+                (&text[span], link.target.unwrap().to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn asset_paths_under_the_roots() {
+        let root = std::env::temp_dir().join(format!("ritobin-lsp-links-{}", std::process::id()));
+        let file = root.join("assets/spell.dds");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, b"").unwrap();
+
+        let worker = Fixture::default()
+            .option("assetRoots", json!([root]))
+            .worker(
+                "a: string = \"ASSETS/Spell.dds\"\nb: string = \"assets/missing.dds\"\nc: string = \"not a path\"\n",
+            );
+        let target = Url::from_file_path(&file).unwrap().to_string();
+        assert_eq!(links(&worker), [("ASSETS/Spell.dds", target.clone())]);
+
+        // found paths are remembered until files change on disk
+        fs::remove_file(&file).unwrap();
+        assert_eq!(links(&worker), [("ASSETS/Spell.dds", target)]);
+        worker.server.asset_files.write().clear();
+        assert!(links(&worker).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn known_classes_link_to_the_wiki() {
+        let worker = Fixture::default()
+            .class("Spell", None, &[])
+            .class("Hidden", None, &[])
+            .class("Secret", None, &[])
+            .forget(&["Hidden", "Secret"])
+            .names_in(HashKind::Type, &["Hidden"])
+            .worker(&format!(
+                "a: embed = Spell {{}}\nb: embed = {} {{}}\nc: embed = {} {{}}\nd: embed = Unknown {{}}\n",
+                hex("Hidden"),
+                hex("Secret"),
+            ));
+        assert_eq!(
+            links(&worker),
+            [
+                ("Spell", wiki_url("Spell")),
+                (hex("Hidden").as_str(), wiki_url("Hidden")),
+            ]
+        );
+    }
+}
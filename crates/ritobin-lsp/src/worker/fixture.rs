@@ -0,0 +1,236 @@
+//! Workers over a hand-written meta dump and hash tables, for testing the language features
+//! against parsed documents.
+
+use std::{io::BufReader, sync::Arc};
+
+use lsp_server::Connection;
+use lsp_types::Url;
+use ltk_hash::fnv1a;
+use paths::{AbsPathBuf, Utf8PathBuf};
+use poro_hash::Hashtable;
+use ritobin_lsp::syntax::{Entries, Tokens, TypeExpr};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+use crate::{
+    config::Config,
+    document::Document,
+    lol_meta::{schema::BinType, service::Classes},
+    server::{Hashes, Server},
+    worker::{Worker, unhash::HashKind},
+};
+
+pub struct Fixture {
+    classes: Value,
+    names: Vec<String>,
+    /// Names that are only in one of the hash tables
+    table_names: Vec<(HashKind, String)>,
+    options: Value,
+    capabilities: Value,
+    workspace_files: Vec<(Url, String)>,
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Self {
+            classes: json!({}),
+            names: Vec::new(),
+            table_names: Vec::new(),
+            options: json!({ "hashPath": "", "metaDumpPath": "" }),
+            capabilities: json!({}),
+            workspace_files: Vec::new(),
+        }
+    }
+}
+
+impl Fixture {
+    /// Adds a class with `fields` given as `(name, ritobin type)`. The class and field names go
+    /// in the hash tables.
+    pub fn class(mut self, name: &str, base: Option<&str>, fields: &[(&str, &str)]) -> Self {
+        let properties = fields
+            .iter()
+            .map(|(field, ty)| (hex(field), property(ty)))
+            .collect::<serde_json::Map<_, _>>();
+        self.classes[hex(name)] = json!({
+            "base": base.map(hex),
+            "secondary_bases": {},
+            "secondary_children": {},
+            "size": 0,
+            "alignment": 0,
+            "is": { "interface": false, "value": false, "secondary_base": false, "unk5": false },
+            "fn": {},
+            "properties": properties,
+        });
+        self.names.push(name.to_owned());
+        self.names
+            .extend(fields.iter().map(|(field, _)| (*field).to_owned()));
+        self
+    }
+
+    /// Sets a value in a class's dump entry, at a `/` separated path like `is/interface` or
+    /// `defaults/<hash>`.
+    pub fn set(mut self, class: &str, path: &str, value: Value) -> Self {
+        let mut slot = &mut self.classes[hex(class)];
+        for key in path.split('/') {
+            slot = &mut slot[key];
+        }
+        *slot = value;
+        self
+    }
+
+    /// Sets `field`'s default value in `class`.
+    pub fn field_default(self, class: &str, field: &str, value: Value) -> Self {
+        let path = format!("defaults/{}", hex(field));
+        self.set(class, &path, value)
+    }
+
+    /// Makes `field` of `class` an instance of `other`, as for `embed` and `pointer` fields.
+    pub fn other_class(self, class: &str, field: &str, other: &str) -> Self {
+        let path = format!("properties/{}/other_class", hex(field));
+        self.set(class, &path, json!(hex(other)))
+    }
+
+    /// Adds names to the hash tables, e.g. for entries and hash values.
+    pub fn names(mut self, names: &[&str]) -> Self {
+        self.names
+            .extend(names.iter().map(|name| (*name).to_owned()));
+        self
+    }
+
+    /// Adds names to just the hash table for `kind`.
+    pub fn names_in(mut self, kind: HashKind, names: &[&str]) -> Self {
+        self.table_names
+            .extend(names.iter().map(|name| (kind, (*name).to_owned())));
+        self
+    }
+
+    /// Drops names from the hash tables, for things only known by their hash.
+    pub fn forget(mut self, names: &[&str]) -> Self {
+        self.names.retain(|name| !names.contains(&name.as_str()));
+        self
+    }
+
+    /// Sets an initialization option, e.g. `inlayHints/types`.
+    pub fn option(mut self, path: &str, value: Value) -> Self {
+        let mut slot = &mut self.options;
+        for key in path.split('/') {
+            slot = &mut slot[key];
+        }
+        *slot = value;
+        self
+    }
+
+    /// Sets the client capabilities, which are empty otherwise.
+    pub fn capabilities(mut self, capabilities: Value) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Adds a file to the workspace index, as if it had been read from disk.
+    pub fn workspace_file(mut self, uri: &str, text: &str) -> Self {
+        let uri = Url::parse(uri).expect("invalid uri");
+        self.workspace_files.push((uri, text.to_owned()));
+        self
+    }
+
+    pub fn server(self) -> Arc<Server> {
+        let options = serde_json::from_value(self.options).expect("invalid init options");
+        let caps = serde_json::from_value(self.capabilities).expect("invalid capabilities");
+        let config = Config::new(
+            AbsPathBuf::assert(Utf8PathBuf::from("/")),
+            caps,
+            Vec::new(),
+            None,
+            Some(options),
+        );
+        let mut server = Server::new(Connection::memory().0, config);
+
+        let table = |kind: HashKind| {
+            let only_here = self
+                .table_names
+                .iter()
+                .filter(|(k, _)| *k == kind)
+                .map(|(_, name)| name);
+            let listing = self
+                .names
+                .iter()
+                .chain(only_here)
+                .map(|name| format!("{:08x} {name}\n", fnv1a::hash_lower(name)))
+                .collect::<String>();
+            Hashtable::read_hashtable_file(&mut BufReader::new(listing.as_bytes())).ok()
+        };
+        server.hashes = Hashes {
+            entries: table(HashKind::Entry),
+            fields: table(HashKind::Field),
+            hashes: table(HashKind::Hash),
+            types: table(HashKind::Type),
+        };
+
+        let classes = serde_json::from_value(self.classes).expect("invalid class dump");
+        *server.meta.classes.write() = Classes::new(classes);
+        server
+            .meta
+            .loaded
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+
+        let server = Arc::new(server);
+        for (uri, text) in self.workspace_files {
+            server.workspace.insert(&server, uri, &text);
+        }
+        server
+    }
+
+    /// A worker with `text` open and its diagnostics collected.
+    pub fn worker(self, text: &str) -> Worker {
+        let uri = Url::parse("file:///test.ritobin").unwrap();
+        let (_, rx) = mpsc::channel(1);
+        let mut worker = Worker {
+            rx,
+            bin: None,
+            entries: Entries::default(),
+            tokens: Tokens::default(),
+            diagnostics: Vec::new(),
+            unresolved_links: Vec::new(),
+            document: Document::new(uri, 0, text.to_owned()),
+            server: self.server(),
+        };
+        worker.update();
+        worker
+    }
+}
+
+/// The `0x..` spelling of a name, as in the dump and in documents.
+pub fn hex(name: &str) -> String {
+    format!("0x{:08x}", fnv1a::hash_lower(name))
+}
+
+/// A dump property for a ritobin type like `map[hash,string]`.
+fn property(ty: &str) -> Value {
+    let ty = TypeExpr::parse(ty);
+    let bin_type = |name: &str| BinType::from_rito_name(name).expect("unknown type");
+    let mut property = json!({
+        "offset": 0,
+        "bitmask": 0,
+        "value_type": bin_type(ty.base),
+        "unkptr": "0x0",
+    });
+    match ty.args[..] {
+        [value] => {
+            property["container"] = json!({
+                "vtable": "0x0",
+                "value_type": bin_type(value),
+                "value_size": 0,
+            });
+        }
+        [key, value] => {
+            property["map"] = json!({
+                "vtable": "0x0",
+                "key_type": bin_type(key),
+                "value_type": bin_type(value),
+                "storage": "StdMap",
+            });
+        }
+        _ => {}
+    }
+    property
+}
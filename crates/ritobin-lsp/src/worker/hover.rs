@@ -0,0 +1,763 @@
+use std::fmt::Write as _;
+
+use lsp_types::{Hover, MarkupContent, MarkupKind, WorkDoneProgressParams};
+use ltk_ritobin::{
+    cst::{Kind as TreeKind, visitor::VisitorExt as _},
+    parse::{Token, TokenKind},
+};
+use poro_hash::BinHash;
+use ritobin_lsp::syntax::{Entry, KeyId, hash_name, is_asset_path, unquote};
+use xxhash_rust::xxh64::xxh64;
+
+use crate::{
+    lol_meta::{
+        schema::{BinType, Property, U32Hash},
+        service::Classes,
+    },
+    lsp::ext::PositionOrRange,
+    worker::{ClassFinder, Worker, unhash::HashKind},
+};
+
+impl Worker {
+    pub fn hover(
+        &self,
+        position: PositionOrRange,
+        _work_done_progress_params: WorkDoneProgressParams,
+    ) -> anyhow::Result<Option<Hover>> {
+        let pos = position.start();
+        let doc = &self.document;
+        let Some((cst, _bin)) = self.bin.as_ref() else {
+            return Ok(None);
+        };
+
+        let finder =
+            ClassFinder::new(doc.line_numbers.from_position(pos), doc.text.clone()).walk(cst);
+        let Some((token, tree)) = finder.found_token else {
+            return Ok(None);
+        };
+        let classes = self.server.meta.classes.read();
+        let class_name = finder
+            .class_stack
+            .last()
+            .map(|(_, class)| (class, hash_name(&doc.text.as_str()[class])));
+
+        let prop = match (class_name, tree) {
+            (Some((_, class_hash)), TreeKind::EntryKey) => {
+                classes.find_declaring(class_hash, hash_name(&doc.text[token.span]))
+            }
+            _ => None,
+        };
+
+        let value = match (class_name, tree, prop) {
+            (Some((class_name_span, class_hash)), TreeKind::EntryKey, Some((declaring, prop))) => {
+                let class_name = &doc.text.as_str()[*class_name_span];
+                let class_name = match class_name.starts_with("0x") {
+                    true => self
+                        .unhashed(HashKind::Type, class_hash)
+                        .unwrap_or(class_name),
+                    false => class_name,
+                };
+                let txt = &doc.text.as_str()[token.span];
+                let hash = hash_name(txt);
+                let name = match token.kind {
+                    TokenKind::HexLit => self.unhashed(HashKind::Field, hash).unwrap_or(txt),
+                    _ => txt,
+                };
+                self.property_hover(
+                    (class_name, U32Hash(class_hash)),
+                    (name, U32Hash(hash)),
+                    declaring,
+                    prop,
+                    &classes,
+                )
+            }
+            (Some((class_name_span, class_hash)), TreeKind::Class, _)
+                if class_name_span.start == token.span.start =>
+            {
+                let class_name = &doc.text.as_str()[*class_name_span];
+                let class_name = match token.kind {
+                    TokenKind::HexLit => self
+                        .unhashed(HashKind::Type, class_hash)
+                        .unwrap_or(class_name),
+                    _ => class_name,
+                };
+                match classes.get(class_hash) {
+                    Some(_) => {
+                        let mut str =
+                            format!("{} (`0x{class_hash:>08x}`)\n\n", wiki_link(class_name));
+
+                        for (d, (hash, _)) in classes.ancestors(class_hash).enumerate().skip(1) {
+                            let base_name = self.unhashed(HashKind::Type, *hash).unwrap_or("??");
+                            writeln!(
+                                str,
+                                "{}└─ {}\n",
+                                "\u{00A0}".repeat(d - 1),
+                                wiki_link(base_name)
+                            )?;
+                        }
+                        if let Some(doc) = self.server.docs.class(class_hash) {
+                            write!(str, "\n---\n\n{doc}\n")?;
+                        }
+
+                        str
+                    }
+                    None => format!("*Unknown class `{class_name}`* (`0x{class_hash:>08x}`)"),
+                }
+            }
+            _ => match self
+                .entry_hover(&token, tree)
+                .or_else(|| self.hash_hover(&token, tree, &classes))
+                .or_else(|| self.value_hover(&token, tree, &classes))
+            {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some(Hover {
+            contents: lsp_types::HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(doc.line_numbers.from_span(token.span)),
+        }))
+    }
+
+    /// Schema details of a class field, including where it is declared and its default.
+    pub(crate) fn property_hover(
+        &self,
+        (class_name, class_hash): (&str, U32Hash),
+        (name, hash): (&str, U32Hash),
+        declaring: U32Hash,
+        prop: &Property,
+        classes: &Classes,
+    ) -> String {
+        let class_link = |hash: U32Hash| match self.unhashed(HashKind::Type, *hash) {
+            Some(name) => wiki_link(name),
+            None => format!("`{hash}`"),
+        };
+
+        let mut str = format!(
+            "### {}\n\n`{name}`: `{}`\n\n`0x{:>08x}`\n\n",
+            wiki_link(class_name),
+            prop.rito_type(),
+            *hash,
+        );
+        if declaring != class_hash {
+            let _ = write!(str, "Declared in {}\n\n", class_link(declaring));
+        }
+
+        let _ = writeln!(str, "Offset: `{:#x}`  ", prop.offset);
+        if prop.bitmask != 0 {
+            let _ = writeln!(str, "Bitmask: `{:#04x}`  ", prop.bitmask);
+        }
+        if let Some(container) = &prop.container {
+            let storage = container
+                .storage
+                .map_or("unknown storage", |s| s.description());
+            let _ = writeln!(str, "Storage: {storage}  ");
+            if let Some(size) = container.fixed_size {
+                let _ = writeln!(str, "Fixed size: `{size}`  ");
+            }
+        }
+        if let Some(map) = &prop.map {
+            let _ = writeln!(str, "Storage: {}  ", map.storage.description());
+        }
+        if let Some(other) = prop.other_class {
+            let _ = writeln!(str, "Class: {}  ", class_link(other));
+        }
+        let default = prop.default_literal(classes.find_default(class_hash, hash), |class| {
+            self.unhashed(HashKind::Type, *class)
+                .map_or_else(|| class.to_string(), |name| name.to_owned())
+        });
+        let _ = writeln!(str, "Default: `{default}`");
+
+        match self.server.docs.field(classes, *class_hash, *hash) {
+            Some(doc) => {
+                let _ = write!(str, "\n---\n\n{doc}\n");
+            }
+            None => str.push_str("\n*No documentation available.*\n"),
+        }
+        str
+    }
+
+    /// Hover for the key of a bin object, with its class, hashes, inbound links and shadowing.
+    fn entry_hover(&self, token: &Token, tree: TreeKind) -> Option<String> {
+        if tree != TreeKind::EntryKey {
+            return None;
+        }
+        let text = self.document.text.as_str();
+        let (idx, entry) = self
+            .entries
+            .objects(text)
+            .find(|(_, e)| e.key == token.span)?;
+        let hash = entry.key_hash(text)?;
+
+        let txt = &text[entry.key];
+        let path = match entry.key_kind {
+            TokenKind::HexLit => self.unhashed(HashKind::Entry, hash),
+            _ => Some(unquote(txt)),
+        };
+        let mut str = match path {
+            Some(path) => format!("`{path}`\n\n"),
+            None => format!("*Unknown {}* `{txt}`\n\n", HashKind::Entry.description()),
+        };
+
+        if let Some(class) = entry.class {
+            let class_name = &text[class];
+            let class_name = match class_name.starts_with("0x") {
+                true => self
+                    .unhashed(HashKind::Type, hash_name(class_name))
+                    .unwrap_or(class_name),
+                false => class_name,
+            };
+            let _ = write!(str, "{}\n\n", wiki_link(class_name));
+        }
+        let _ = write!(str, "FNV-1a: `0x{hash:08x}`\n\n");
+
+        let local = self
+            .entries
+            .link_values(&self.tokens, text)
+            .into_iter()
+            .filter(|t| KeyId::new(&text[t.span], t.kind, true) == KeyId::Hash(hash))
+            .count();
+        let other = self.server.workspace.link_count(hash, &self.document.uri);
+        let _ = match (local, other) {
+            (0, 0) => writeln!(str, "No links to this entry"),
+            (local, 0) => writeln!(str, "{local} link(s) to this entry in this file"),
+            (local, other) => writeln!(
+                str,
+                "{} link(s) to this entry, {other} in other files",
+                local + other
+            ),
+        };
+
+        // later definitions of the same object replace earlier ones
+        let line = |e: &Entry| self.document.line_numbers.from_span(e.key).start.line + 1;
+        let same = self
+            .entries
+            .objects(text)
+            .filter(|(i, e)| *i != idx && e.key_hash(text) == Some(hash))
+            .collect::<Vec<_>>();
+        if let Some((_, shadowed)) = same.iter().rev().find(|(i, _)| *i < idx) {
+            let _ = write!(str, "\nShadows the definition on line {}\n", line(shadowed));
+        }
+        if let Some((_, shadower)) = same.iter().find(|(i, _)| *i > idx) {
+            let _ = write!(
+                str,
+                "\nShadowed by the definition on line {}\n",
+                line(shadower)
+            );
+        }
+        Some(str)
+    }
+
+    /// Hover for hex literals and for names that stand for a hash, showing the other form.
+    fn hash_hover(&self, token: &Token, tree: TreeKind, classes: &Classes) -> Option<String> {
+        let text = self.document.text.as_str();
+        let kind = self.hash_kind(token, tree, classes);
+
+        match token.kind {
+            TokenKind::HexLit => {
+                let txt = &text[token.span];
+                if kind == Some(HashKind::File) {
+                    return Some(format!(
+                        "`{txt}`\n\n*{}* - game file paths can't be unhashed",
+                        HashKind::File.description()
+                    ));
+                }
+                let hash = u32::from_str_radix(txt.strip_prefix("0x")?, 16).ok()?;
+
+                // without any context, the hash could come from any table
+                let candidates = match kind {
+                    Some(kind) => vec![kind],
+                    None => vec![
+                        HashKind::Entry,
+                        HashKind::Hash,
+                        HashKind::Field,
+                        HashKind::Type,
+                    ],
+                };
+                let found = candidates
+                    .iter()
+                    .find_map(|kind| Some((*kind, self.unhashed(*kind, hash)?)));
+                Some(match (found, kind) {
+                    (Some((kind, name)), _) => format!(
+                        "`{name}`\n\n{} `{txt}` from `{}`",
+                        kind.description(),
+                        kind.table_file()?
+                    ),
+                    (None, Some(kind)) => format!(
+                        "*Unknown {}* - `{txt}` is not in `{}`",
+                        kind.description(),
+                        kind.table_file()?
+                    ),
+                    (None, None) => format!("*Unknown hash* - `{txt}` is not in any hash table"),
+                })
+            }
+            TokenKind::Name | TokenKind::String
+                if token.kind == TokenKind::String
+                    || matches!(tree, TreeKind::Class | TreeKind::EntryKey) =>
+            {
+                let kind = kind?;
+                let name = unquote(&text[token.span]);
+                if name.is_empty() {
+                    return None;
+                }
+                Some(match kind {
+                    HashKind::File => format!(
+                        "`{name}`\n\n{}\n\nxxh64: `0x{:016x}`",
+                        kind.description(),
+                        xxh64(name.to_ascii_lowercase().as_bytes(), 0)
+                    ),
+                    _ => format!(
+                        "`{name}`\n\n{}\n\nFNV-1a: `0x{:08x}`",
+                        kind.description(),
+                        ltk_hash::fnv1a::hash_lower(name)
+                    ),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Hover for type expressions and literal values.
+    fn value_hover(&self, token: &Token, tree: TreeKind, classes: &Classes) -> Option<String> {
+        let text = self.document.text.as_str();
+        let txt = &text[token.span];
+
+        match token.kind {
+            TokenKind::Name
+                if matches!(
+                    tree,
+                    TreeKind::TypeExpr | TreeKind::TypeArg | TreeKind::TypeArgList
+                ) =>
+            {
+                self.type_hover(token, tree, classes)
+            }
+            TokenKind::String => {
+                let path = unquote(txt);
+                is_asset_path(path).then(|| {
+                    format!(
+                        "`{path}`\n\nAsset path\n\nxxh64: `0x{:016x}`",
+                        xxh64(path.to_ascii_lowercase().as_bytes(), 0)
+                    )
+                })
+            }
+            TokenKind::Number | TokenKind::LCurly | TokenKind::RCurly => {
+                let vector = self.vector_hover(token, classes);
+                let number = match token.kind {
+                    TokenKind::Number => number_hover(txt),
+                    _ => None,
+                };
+                match (vector, number) {
+                    (Some(vector), Some(number)) => Some(format!("{vector}\n\n---\n\n{number}")),
+                    (vector, number) => vector.or(number),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Description of a type in a type expression, with the container storage of the field
+    /// it belongs to when the schema knows it.
+    fn type_hover(&self, token: &Token, tree: TreeKind, classes: &Classes) -> Option<String> {
+        let text = self.document.text.as_str();
+        let name = &text[token.span];
+        let ty = BinType::from_rito_name(name)?;
+
+        let mut str = format!("`{name}`\n\n{}", ty.description());
+        if let Some(size) = ty.size() {
+            let _ = write!(str, " - {size} bytes");
+        }
+
+        // only the base type says anything about how the field is stored
+        if tree != TreeKind::TypeExpr {
+            return Some(str);
+        }
+        let prop = self
+            .entries
+            .at(token.span.start)
+            .and_then(|(_, entry)| self.property(entry, classes));
+        if let Some(container) = prop.and_then(|p| p.container.as_ref()) {
+            let storage = container
+                .storage
+                .map_or("unknown storage", |s| s.description());
+            let _ = write!(str, "\n\nStored as {storage}");
+            if let Some(size) = container.fixed_size {
+                let _ = write!(str, " of {size} items");
+            }
+        }
+        if let Some(map) = prop.and_then(|p| p.map.as_ref()) {
+            let _ = write!(str, "\n\nStored as {}", map.storage.description());
+        }
+        Some(str)
+    }
+
+    /// Component breakdown of the vector or colour literal around `token`, with a swatch for
+    /// colours.
+    fn vector_hover(&self, token: &Token, classes: &Classes) -> Option<String> {
+        let text = self.document.text.as_str();
+        let offset = token.span.start;
+
+        let (idx, entry) = self.entries.at(offset)?;
+        let ty = match entry.is_field() {
+            true => self.element_type(idx, classes, false)?,
+            // map values are typed by the map
+            false => self.element_type(entry.parent?, classes, false)?,
+        };
+        let labels = ty.components()?;
+
+        // the innermost block is the vector itself, as long as it doesn't hold more blocks
+        let block = self
+            .entries
+            .blocks
+            .iter()
+            .filter(|b| b.span.start <= offset && offset < b.span.end)
+            .min_by_key(|b| b.span.end - b.span.start)?;
+        let tokens = self
+            .tokens
+            .in_span(block.span)
+            .map(|(t, _)| t)
+            .collect::<Vec<_>>();
+        if tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::LCurly)
+            .count()
+            != 1
+        {
+            return None;
+        }
+        let values = tokens
+            .into_iter()
+            .filter(|t| t.kind == TokenKind::Number)
+            .collect::<Vec<_>>();
+        if values.len() != labels.len() {
+            return None;
+        }
+
+        let mut str = String::new();
+        if ty == BinType::Color {
+            let channels = values
+                .iter()
+                .map(|t| text[t.span].parse::<u8>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let [r, g, b, a] = channels[..] else {
+                return None;
+            };
+            let _ = write!(
+                str,
+                "<span style=\"color:#{r:02x}{g:02x}{b:02x};\">████</span> `#{r:02x}{g:02x}{b:02x}{a:02x}`\n\n"
+            );
+        }
+        for (label, value) in labels.iter().zip(values) {
+            let value_txt = &text[value.span];
+            match value.span == token.span {
+                true => {
+                    let _ = writeln!(str, "**{label}**: `{value_txt}`  ");
+                }
+                false => {
+                    let _ = writeln!(str, "{label}: `{value_txt}`  ");
+                }
+            }
+        }
+        Some(str.trim_end().into())
+    }
+
+    /// The name of a class, or its hash if we can't unhash it.
+    pub(crate) fn type_name(&self, hash: u32) -> String {
+        self.unhashed(HashKind::Type, hash)
+            .map_or_else(|| format!("0x{hash:08x}"), |name| name.to_owned())
+    }
+
+    /// The name a hash stands for, from the matching hash table.
+    pub(crate) fn unhashed(&self, kind: HashKind, hash: u32) -> Option<&str> {
+        self.hash_table_for(kind)?
+            .hashes
+            .get(&BinHash(hash))
+            .map(|s| s.as_str())
+    }
+}
+
+/// Decimal, hex and float bit views of a number literal.
+fn number_hover(txt: &str) -> Option<String> {
+    if let Ok(n) = txt.parse::<i64>() {
+        // negative numbers are shown as their 32-bit two's complement when they fit
+        let hex = match i32::try_from(n) {
+            Ok(n) if n < 0 => format!("0x{:08x}", n as u32),
+            _ => format!("{n:#x}"),
+        };
+        let mut str = format!("`{n}`\n\nhex: `{hex}`");
+        if let Ok(bits) = u32::try_from(n).or_else(|_| i32::try_from(n).map(|n| n as u32)) {
+            let _ = write!(str, "\n\nas f32 bits: `{:?}`", f32::from_bits(bits));
+        }
+        return Some(str);
+    }
+
+    let f = txt.parse::<f32>().ok()?;
+    Some(format!(
+        "`{txt}`\n\nf32: `{f:?}`\n\nbits: `0x{:08x}`",
+        f.to_bits()
+    ))
+}
+
+/// Markdown link to a class's page on the meta wiki.
+fn wiki_link(class_name: &str) -> String {
+    format!("[{class_name}]({})", wiki_url(class_name))
+}
+
+/// A class's page on the meta wiki.
+pub(crate) fn wiki_url(class_name: &str) -> String {
+    format!(
+        "https://meta-wiki.leaguetoolkit.dev/classes/{}/",
+        class_name.to_ascii_lowercase()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::HoverContents;
+
+    use super::*;
+    use crate::worker::fixture::{Fixture, hex};
+
+    /// The hover at the start of the first occurrence of `needle`, and the text its range
+    /// covers.
+    fn hover(worker: &Worker, needle: &str) -> Option<(String, String)> {
+        let text = worker.document.text.as_str();
+        let offset = text.find(needle).expect("needle not in text") as u32;
+        let position = worker.document.line_numbers.position(offset);
+        let hover = worker
+            .hover(PositionOrRange::Position(position), Default::default())
+            .unwrap()?;
+        let HoverContents::Markup(content) = hover.contents else {
+            panic!("expected markdown: {hover:?}");
+        };
+        let range = worker
+            .document
+            .line_numbers
+            .from_range(&hover.range.unwrap());
+        Some((content.value, text[range].to_owned()))
+    }
+
+    #[test]
+    fn number_views() {
+        let int = number_hover("42").unwrap();
+        assert!(int.contains("`0x2a`"), "{int}");
+        let negative = number_hover("-1").unwrap();
+        assert!(negative.contains("`0xffffffff`"), "{negative}");
+        let float = number_hover("1.0").unwrap();
+        assert!(float.contains("`0x3f800000`"), "{float}");
+        assert!(number_hover("abc").is_none());
+    }
+
+    #[test]
+    fn hex_literals_from_each_table() {
+        let text = format!(
+            "entries: map[hash,embed] = {{
+    \"Characters/Test\" = Spell {{
+        {}: hash = {}
+        mLink: link = {}
+        mType: u32 = {}
+        mOther: hash = 0xdeadbeef
+        mCount: u32 = 0x0badf00d
+        mFile: file = 0x1234abcd
+    }}
+}}
+",
+            hex("mValue"),
+            hex("HashValue"),
+            hex("Characters/Linked"),
+            hex("Spell"),
+        );
+        let worker = Fixture::default()
+            .names_in(HashKind::Entry, &["Characters/Linked"])
+            .names_in(HashKind::Hash, &["HashValue"])
+            .names_in(HashKind::Field, &["mValue"])
+            .names_in(HashKind::Type, &["Spell"])
+            .worker(&text);
+
+        let found = |name: &str, kind: &str, table: &str| {
+            Some((
+                format!("`{name}`\n\n{kind} `{}` from `{table}`", hex(name)),
+                hex(name),
+            ))
+        };
+        assert_eq!(
+            hover(&worker, &hex("mValue")),
+            found("mValue", "Field name", "hashes.binfields.txt")
+        );
+        assert_eq!(
+            hover(&worker, &hex("HashValue")),
+            found("HashValue", "Hash", "hashes.binhashes.txt")
+        );
+        assert_eq!(
+            hover(&worker, &hex("Characters/Linked")),
+            found("Characters/Linked", "Entry name", "hashes.binentries.txt")
+        );
+        // a u32 could hold any hash, so every table is searched
+        assert_eq!(
+            hover(&worker, &hex("Spell")),
+            found("Spell", "Class name", "hashes.bintypes.txt")
+        );
+
+        assert_eq!(
+            hover(&worker, "0xdeadbeef").unwrap().0,
+            "*Unknown Hash* - `0xdeadbeef` is not in `hashes.binhashes.txt`"
+        );
+        assert_eq!(
+            hover(&worker, "0x0badf00d").unwrap().0,
+            "*Unknown hash* - `0x0badf00d` is not in any hash table"
+        );
+        assert_eq!(
+            hover(&worker, "0x1234abcd").unwrap().0,
+            "`0x1234abcd`\n\n*File path* - game file paths can't be unhashed"
+        );
+    }
+
+    #[test]
+    fn names_show_their_hash() {
+        let text = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Spell {
+        mValue: hash = "HashValue"
+        mFile: file = "Data/File.bin"
+    }
+}
+"#;
+        let worker = Fixture::default().worker(text);
+        assert_eq!(
+            hover(&worker, "mValue"),
+            Some((
+                format!("`mValue`\n\nField name\n\nFNV-1a: `{}`", hex("mValue")),
+                "mValue".to_owned()
+            ))
+        );
+        assert_eq!(
+            hover(&worker, "\"HashValue\""),
+            Some((
+                format!("`HashValue`\n\nHash\n\nFNV-1a: `{}`", hex("HashValue")),
+                "\"HashValue\"".to_owned()
+            ))
+        );
+        assert_eq!(
+            hover(&worker, "\"Data/File.bin\"").unwrap().0,
+            format!(
+                "`Data/File.bin`\n\nFile path\n\nxxh64: `0x{:016x}`",
+                xxh64(b"data/file.bin", 0)
+            )
+        );
+    }
+
+    #[test]
+    fn types_vectors_and_asset_paths() {
+        let text = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Spell {
+        mCount: u32 = 1
+        mPos: vec3 = { 7, 8, 9 }
+        mTint: rgba = { 255, 0, 0, 255 }
+        mTexture: string = "ASSETS/Spell.dds"
+    }
+}
+"#;
+        let worker = Fixture::default().worker(text);
+        assert_eq!(
+            hover(&worker, "u32 = 1").unwrap().0,
+            "`u32`\n\nUnsigned 32-bit integer - 4 bytes"
+        );
+
+        let (vector, range) = hover(&worker, "8,").unwrap();
+        assert!(
+            vector.starts_with("x: `7`  \n**y**: `8`  \nz: `9`\n\n---\n\n`8`"),
+            "{vector}"
+        );
+        assert_eq!(range, "8");
+
+        let color = hover(&worker, "255, 0").unwrap().0;
+        assert!(
+            color.starts_with("<span style=\"color:#ff0000;\">████</span> `#ff0000ff`"),
+            "{color}"
+        );
+
+        assert_eq!(
+            hover(&worker, "\"ASSETS/Spell.dds\"").unwrap().0,
+            format!(
+                "`ASSETS/Spell.dds`\n\nAsset path\n\nxxh64: `0x{:016x}`",
+                xxh64(b"assets/spell.dds", 0)
+            )
+        );
+    }
+
+    #[test]
+    fn inherited_property() {
+        let worker = Fixture::default()
+            .class("Base", None, &[("mInner", "embed")])
+            .other_class("Base", "mInner", "Inner")
+            .class("Child", Some("Base"), &[("mName", "string")])
+            .class("Inner", None, &[])
+            .worker(
+                "entries: map[hash,embed] = {\n    \"Characters/Test\" = Child {\n        mInner: embed = Inner {}\n    }\n}\n",
+            );
+        let (value, range) = hover(&worker, "mInner").unwrap();
+        assert_eq!(range, "mInner");
+        assert!(
+            value.starts_with(&format!(
+                "### {}\n\n`mInner`: `embed`\n\n`{}`\n\nDeclared in {}\n\n",
+                wiki_link("Child"),
+                hex("mInner"),
+                wiki_link("Base")
+            )),
+            "{value}"
+        );
+        assert!(
+            value.contains(&format!("Class: {}  \n", wiki_link("Inner"))),
+            "{value}"
+        );
+        assert!(value.contains("Default: `Inner {}`"), "{value}");
+    }
+
+    #[test]
+    fn entry_keys() {
+        let text = format!(
+            r#"entries: map[hash,embed] = {{
+    "Characters/Test" = Spell {{}}
+    "Characters/Other" = Spell {{
+        mLink: link = "Characters/Test"
+        mLinks: list[link] = {{ "Characters/Test" }}
+    }}
+    {} = Spell {{}}
+}}
+"#,
+            hex("Characters/Test")
+        );
+        let worker = Fixture::default()
+            .names(&["Characters/Test"])
+            .workspace_file(
+                "file:///other.rito",
+                "entries: map[hash,embed] = {\n    \"X\" = Spell {\n        mLink: link = \"Characters/Test\"\n    }\n}\n",
+            )
+            .worker(&text);
+
+        let header = format!(
+            "`Characters/Test`\n\n{}\n\nFNV-1a: `{}`\n\n3 link(s) to this entry, 1 in other files\n",
+            wiki_link("Spell"),
+            hex("Characters/Test")
+        );
+        assert_eq!(
+            hover(&worker, "\"Characters/Test\""),
+            Some((
+                format!("{header}\nShadowed by the definition on line 7\n"),
+                "\"Characters/Test\"".to_owned()
+            ))
+        );
+        assert_eq!(
+            hover(&worker, &hex("Characters/Test")),
+            Some((
+                format!("{header}\nShadows the definition on line 2\n"),
+                hex("Characters/Test")
+            ))
+        );
+
+        let (other, _) = hover(&worker, "\"Characters/Other\"").unwrap();
+        assert!(other.ends_with("No links to this entry\n"), "{other}");
+    }
+}
@@ -0,0 +1,232 @@
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, InlayHintTooltip, Range};
+use ltk_ritobin::{
+    parse::{Span, TokenKind},
+    typecheck::visitor::RitoType,
+};
+use ritobin_lsp::syntax::quote;
+
+use crate::{
+    lol_meta::service::Classes,
+    worker::{Worker, unhash::HashKind},
+};
+
+impl Worker {
+    pub fn inlay_hints(&self, range: Range) -> Vec<InlayHint> {
+        let options = self.server.config.inlay_hints();
+        let span = self.document.line_numbers.from_range(&range);
+        let classes = self.server.meta.classes.read();
+
+        let mut hints = Vec::new();
+        if options.unhashed_names {
+            self.unhashed_name_hints(span, &classes, &mut hints);
+        }
+        if options.types {
+            self.type_hints(span, &classes, &mut hints);
+        }
+        if options.vector_components {
+            self.component_hints(span, &classes, &mut hints);
+        }
+        hints.sort_by_key(|h| (h.position.line, h.position.character));
+        hints
+    }
+
+    /// The name after every `0x..` hash that the hash tables know.
+    fn unhashed_name_hints(&self, span: Span, classes: &Classes, hints: &mut Vec<InlayHint>) {
+        let text = self.document.text.as_str();
+        for (token, tree) in self.tokens.in_span(span) {
+            if token.kind != TokenKind::HexLit {
+                continue;
+            }
+            let Some(kind) = self.hash_kind(token, *tree, classes) else {
+                continue;
+            };
+            let Some(name) = u32::from_str_radix(&text[token.span][2..], 16)
+                .ok()
+                .and_then(|hash| self.unhashed(kind, hash))
+            else {
+                continue;
+            };
+            let label = match kind {
+                HashKind::Type | HashKind::Field => name.to_owned(),
+                _ => quote(name),
+            };
+            hints.push(InlayHint {
+                position: self.document.line_numbers.position(token.span.end),
+                label: InlayHintLabel::String(label),
+                kind: None,
+                text_edits: None,
+                tooltip: Some(InlayHintTooltip::String(kind.description().to_owned())),
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            });
+        }
+    }
+
+    /// The value type after the keys of map entries, which unlike fields aren't written out.
+    fn type_hints(&self, span: Span, classes: &Classes, hints: &mut Vec<InlayHint>) {
+        let text = self.document.text.as_str();
+        for (_, entry) in self.entries.iter() {
+            if entry.is_field() || !(span.start <= entry.key.start && entry.key.end <= span.end) {
+                continue;
+            }
+            let Some(parent) = entry.parent else {
+                continue;
+            };
+            // the map's type expression keeps the value type's own subtypes, which the schema
+            // doesn't have
+            let written = self.entries.entries[parent]
+                .type_expr
+                .and_then(|ty| map_value_type(&text[ty]));
+            let ty = match written {
+                Some(ty) => ty.to_owned(),
+                None => match self.element_type(parent, classes, false) {
+                    Some(ty) => RitoType::simple(ty.into()).to_string(),
+                    None => continue,
+                },
+            };
+            hints.push(InlayHint {
+                position: self.document.line_numbers.position(entry.key.end),
+                label: InlayHintLabel::String(format!(": {ty}")),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: None,
+                tooltip: None,
+                padding_left: None,
+                padding_right: None,
+                data: None,
+            });
+        }
+    }
+
+    /// `x:`, `y:`, .. before the components of vector and colour values.
+    fn component_hints(&self, span: Span, classes: &Classes, hints: &mut Vec<InlayHint>) {
+        for literal in self.vector_literals(classes) {
+            let Some(components) = literal.ty.components() else {
+                continue;
+            };
+            let labelled = components.iter().zip(&literal.components);
+            for (label, component) in
+                labelled.filter(|(_, c)| span.start <= c.start && c.end <= span.end)
+            {
+                hints.push(InlayHint {
+                    position: self.document.line_numbers.position(component.start),
+                    label: InlayHintLabel::String(format!("{label}:")),
+                    kind: Some(InlayHintKind::PARAMETER),
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: None,
+                    padding_right: Some(true),
+                    data: None,
+                });
+            }
+        }
+    }
+}
+
+/// The value type written in a map type expression, e.g. `list[string]` in
+/// `map[hash, list[string]]`.
+fn map_value_type(type_expr: &str) -> Option<&str> {
+    let (base, args) = type_expr.split_once('[')?;
+    if base.trim() != "map" {
+        return None;
+    }
+    let args = args.trim_end().strip_suffix(']')?;
+    let mut depth = 0;
+    let comma = args.find(|c| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        c == ',' && depth == 0
+    })?;
+    Some(args[comma + 1..].trim()).filter(|ty| !ty.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::worker::fixture::{Fixture, hex};
+
+    /// The labels of the hints in the whole of `text`.
+    fn hints(fixture: Fixture, text: &str) -> Vec<String> {
+        let worker = fixture.worker(text);
+        let end = worker.document.line_numbers.position(text.len() as u32);
+        worker
+            .inlay_hints(Range::new(Default::default(), end))
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => label,
+                InlayHintLabel::LabelParts(parts) => parts.into_iter().map(|p| p.value).collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hint_kinds_and_toggles() {
+        let text = format!(
+            r#"entries: map[hash,embed] = {{
+    "Characters/Test" = Holder {{
+        {}: string = ""
+        mScales: map[hash,vec2] = {{
+            "a" = {{ 1, 2 }}
+        }}
+    }}
+}}
+"#,
+            hex("mName")
+        );
+        let fixture = || Fixture::default().names(&["mName"]);
+
+        assert_eq!(
+            hints(fixture(), &text),
+            [": embed", "mName", ": vec2", "x:", "y:"]
+        );
+        assert_eq!(
+            hints(
+                fixture().option("inlayHints/unhashedNames", json!(false)),
+                &text
+            ),
+            [": embed", ": vec2", "x:", "y:"]
+        );
+        assert_eq!(
+            hints(fixture().option("inlayHints/types", json!(false)), &text),
+            ["mName", "x:", "y:"]
+        );
+        assert_eq!(
+            hints(
+                fixture().option("inlayHints/vectorComponents", json!(false)),
+                &text
+            ),
+            [": embed", "mName", ": vec2"]
+        );
+    }
+
+    #[test]
+    fn unhashed_strings_are_quoted() {
+        let text = format!(
+            "entries: map[hash,embed] = {{\n    \"Characters/Test\" = Holder {{\n        mValue: hash = {}\n    }}\n}}\n",
+            hex("Say \"hi\"")
+        );
+        let hints = hints(
+            Fixture::default()
+                .names(&["Say \"hi\""])
+                .option("inlayHints/types", json!(false)),
+            &text,
+        );
+        assert_eq!(hints, [r#""Say \"hi\"""#]);
+    }
+
+    #[test]
+    fn map_value_types() {
+        assert_eq!(map_value_type("map[hash, embed]"), Some("embed"));
+        assert_eq!(
+            map_value_type("map[hash,list[string]]"),
+            Some("list[string]")
+        );
+        assert_eq!(map_value_type("map[hash]"), None);
+        assert_eq!(map_value_type("list[string]"), None);
+    }
+}
@@ -0,0 +1,614 @@
+use std::collections::hash_map;
+
+use itertools::Itertools as _;
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString};
+use ltk_ritobin::parse::Token;
+use poro_hash::BinHash;
+use ritobin_lsp::syntax::{Entries, Entry, KeyId, Tokens, TypeExpr, hash_name, unquote};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde_json::Value;
+
+use crate::{
+    lol_meta::schema::{BinType, Property},
+    worker::diagnostics::DiagnosticCollector,
+};
+
+impl DiagnosticCollector<'_> {
+    /// Fields set twice in the same class body, and duplicate keys in the same map, including
+    /// bin objects written once by name and once by hash.
+    ///
+    /// Top-level entries are already covered by `ShadowedEntry`.
+    pub fn duplicate_keys(&self, entries: &Entries) -> Vec<Diagnostic> {
+        let text = self.document.text.as_str();
+        let mut seen: FxHashMap<(usize, KeyId), &Entry> = FxHashMap::default();
+        let mut diagnostics = Vec::new();
+
+        for (_, entry) in entries.iter() {
+            let Some(block) = entry.block else {
+                continue;
+            };
+            let string_keyed = entry
+                .parent
+                .and_then(|p| entries.entries[p].type_expr)
+                .is_some_and(|ty| is_string_keyed_map(&text[ty]));
+            let key = KeyId::new(&text[entry.key], entry.key_kind, !string_keyed);
+
+            let first = match seen.entry((block, key)) {
+                hash_map::Entry::Occupied(first) => *first.get(),
+                hash_map::Entry::Vacant(slot) => {
+                    slot.insert(entry);
+                    continue;
+                }
+            };
+
+            let (code, message) = match entry.is_field() {
+                true => (
+                    "duplicate-field",
+                    format!("Field '{}' is already set in this block", &text[entry.key]),
+                ),
+                false => (
+                    "duplicate-key",
+                    format!("Duplicate map key '{}'", &text[entry.key]),
+                ),
+            };
+            diagnostics.push(Diagnostic {
+                range: self.document.line_numbers.from_span(entry.key),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(code.into())),
+                message,
+                related_information: Some(vec![
+                    self.related(first.key, format!("'{}' first set here", &text[first.key])),
+                ]),
+                ..Default::default()
+            });
+        }
+
+        diagnostics
+    }
+
+    /// Links that don't name an object in this file, the workspace, or the known game entries.
+    pub fn unresolved_links<'t>(&self, entries: &Entries, tokens: &'t Tokens) -> Vec<&'t Token> {
+        let text = self.document.text.as_str();
+        let local: FxHashSet<u32> = entries
+            .objects(text)
+            .filter_map(|(_, e)| e.key_hash(text))
+            .collect();
+        let known = self.server.hashes.entries.as_ref();
+
+        entries
+            .link_values(tokens, text)
+            .into_iter()
+            .filter(|t| {
+                let txt = &text[t.span];
+                // empty strings and 0x0 are null links
+                if unquote(txt).is_empty() {
+                    return false;
+                }
+                let KeyId::Hash(hash) = KeyId::new(txt, t.kind, true) else {
+                    return false;
+                };
+                hash != 0
+                    && !local.contains(&hash)
+                    && !self
+                        .server
+                        .workspace
+                        .contains_object(hash, &self.document.uri)
+                    && !known.is_some_and(|k| k.hashes.contains_key(&BinHash(hash)))
+            })
+            .collect()
+    }
+
+    pub fn unresolved_link_diagnostics(&self, links: &[&Token]) -> Vec<Diagnostic> {
+        links
+            .iter()
+            .map(|t| Diagnostic {
+                range: self.document.line_numbers.from_span(t.span),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String("unresolved-link".into())),
+                message: format!(
+                    "Unresolved link {} - no entry with this name in this file, the workspace or the known game entries",
+                    &self.document.text.as_str()[t.span]
+                ),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Fields explicitly set to the default value from the class schema, which can be dropped.
+    pub fn default_values(&self, entries: &Entries) -> Vec<Diagnostic> {
+        let text = self.document.text.as_str();
+        let classes = self.server.meta.classes.read();
+        let mut diagnostics = Vec::new();
+
+        for (idx, block) in entries.blocks.iter().enumerate() {
+            let Some(class) = block.class.map(|c| hash_name(&text[c])) else {
+                continue;
+            };
+            for field in entries.fields_in(idx) {
+                let (Some(key), Some(value)) = (field.key_hash(text), field.value) else {
+                    continue;
+                };
+                let Some(default) = classes.find_default(class, key) else {
+                    continue;
+                };
+                if !matches_default(&text[value], default) {
+                    continue;
+                }
+                diagnostics.push(Diagnostic {
+                    range: self.document.line_numbers.from_span(field.span),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    code: Some(NumberOrString::String("default-value".into())),
+                    message: format!("'{}' is set to its default value", &text[field.key]),
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    ..Default::default()
+                });
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Fields the class schema doesn't know about, and fields typed differently than in the
+    /// schema.
+    pub fn schema_fields(&self, entries: &Entries) -> Vec<Diagnostic> {
+        let text = self.document.text.as_str();
+        let classes = self.server.meta.classes.read();
+        let mut diagnostics = Vec::new();
+
+        for (idx, block) in entries.blocks.iter().enumerate() {
+            let Some(class_name) = block.class else {
+                continue;
+            };
+            let class = hash_name(&text[class_name]);
+            if classes.get(class).is_none() {
+                continue;
+            }
+            for field in entries.fields_in(idx) {
+                let Some(key) = field.key_hash(text) else {
+                    continue;
+                };
+                let Some(prop) = classes.find_property(class, key) else {
+                    diagnostics.push(Diagnostic {
+                        range: self.document.line_numbers.from_span(field.key),
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: Some(NumberOrString::String("unknown-field".into())),
+                        message: format!(
+                            "Unknown field '{}' on {}",
+                            &text[field.key], &text[class_name]
+                        ),
+                        ..Default::default()
+                    });
+                    continue;
+                };
+                let Some(type_expr) = field.type_expr else {
+                    continue;
+                };
+                if matches_property(&text[type_expr], prop) == Some(false) {
+                    diagnostics.push(Diagnostic {
+                        range: self.document.line_numbers.from_span(type_expr),
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: Some(NumberOrString::String("schema-type-mismatch".into())),
+                        message: format!(
+                            "'{}' is declared as {} in {}",
+                            &text[field.key],
+                            prop.rito_type(),
+                            &text[class_name]
+                        ),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Fields that have no default value but are never set, if enabled in the config.
+    pub fn missing_fields(&self, entries: &Entries) -> Vec<Diagnostic> {
+        if !self.server.config.diagnostics_missing_fields() {
+            return Vec::new();
+        }
+
+        let text = self.document.text.as_str();
+        let classes = self.server.meta.classes.read();
+        let hashes = self.server.hashes.fields.as_ref();
+        let mut diagnostics = Vec::new();
+
+        for (idx, block) in entries.blocks.iter().enumerate() {
+            let Some(class_name) = block.class else {
+                continue;
+            };
+            let set: FxHashSet<u32> = entries
+                .fields_in(idx)
+                .filter_map(|e| e.key_hash(text))
+                .collect();
+
+            let class_hash = hash_name(&text[class_name]);
+            let mut missing = Vec::new();
+            // properties redeclared further down the base chain
+            let mut seen = FxHashSet::default();
+            for (_, class) in classes.ancestors(class_hash) {
+                for k in class.properties.keys() {
+                    // without defaults in the dump we can't tell which fields are required
+                    if !seen.insert(*k) || class.defaults.is_none() || set.contains(&k.0) {
+                        continue;
+                    }
+                    // a subclass may give the property a default value
+                    if classes.find_default(class_hash, *k).is_none() {
+                        missing.push(
+                            hashes
+                                .and_then(|h| h.hashes.get(&BinHash(k.0)).cloned())
+                                .unwrap_or_else(|| k.to_string()),
+                        );
+                    }
+                }
+            }
+            if missing.is_empty() {
+                continue;
+            }
+
+            diagnostics.push(Diagnostic {
+                range: self.document.line_numbers.from_span(class_name),
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String("missing-field".into())),
+                message: format!(
+                    "Fields with no default value are never set: {}",
+                    missing.iter().sorted().join(", ")
+                ),
+                ..Default::default()
+            });
+        }
+
+        diagnostics
+    }
+}
+
+/// Whether a value literal is equal to a default value from the meta dump.
+fn matches_default(value: &str, default: &Value) -> bool {
+    let value = value.trim();
+    match default {
+        Value::Null => is_empty_block(value),
+        Value::Bool(b) => value.parse::<bool>().is_ok_and(|v| v == *b),
+        // floats in the dump are f32s widened to f64, so compare at f32 precision
+        Value::Number(n) => parse_number(value)
+            .zip(n.as_f64())
+            .is_some_and(|(v, d)| v as f32 == d as f32),
+        Value::String(s) => match s.starts_with("0x") {
+            true => !unquote(value).is_empty() && hash_name(value) == hash_name(s),
+            false => unquote(value) == s,
+        },
+        Value::Array(items) => {
+            let Some(inner) = value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) else {
+                return false;
+            };
+            // nested blocks (e.g. mtx44 rows) aren't worth splitting properly
+            if inner.contains('{') {
+                return false;
+            }
+            let parts = inner
+                .split([',', '\n'])
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .collect_vec();
+            parts.len() == items.len()
+                && parts
+                    .iter()
+                    .zip(items)
+                    .all(|(part, item)| matches_default(part, item))
+        }
+        Value::Object(fields) => fields.is_empty() && is_empty_block(value),
+    }
+}
+
+fn is_empty_block(value: &str) -> bool {
+    value
+        .strip_prefix('{')
+        .and_then(|v| v.strip_suffix('}'))
+        .is_some_and(|v| v.trim().is_empty())
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+        None => value.parse().ok(),
+    }
+}
+
+/// Whether a written type expression is the type of a schema property, or `None` if it names a
+/// type we don't know, which the typechecker already reports.
+fn matches_property(type_expr: &str, prop: &Property) -> Option<bool> {
+    let ty = TypeExpr::parse(type_expr);
+    let args = ty
+        .args
+        .iter()
+        .map(|arg| BinType::from_rito_name(arg))
+        .collect::<Option<Vec<_>>>()?;
+    let expected = match (&prop.container, &prop.map) {
+        (Some(container), _) => vec![container.value_type],
+        (_, Some(map)) => vec![map.key_type, map.value_type],
+        (None, None) => Vec::new(),
+    };
+    Some(BinType::from_rito_name(ty.base)? == prop.value_type && args == expected)
+}
+
+fn is_string_keyed_map(type_expr: &str) -> bool {
+    type_expr
+        .split_whitespace()
+        .collect::<String>()
+        .starts_with("map[string,")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::worker::{
+        Worker,
+        fixture::{Fixture, hex},
+    };
+
+    /// The code and key text of each diagnostic.
+    fn codes(worker: &Worker, diagnostics: Vec<Diagnostic>) -> Vec<(String, &str)> {
+        let text = worker.document.text.as_str();
+        diagnostics
+            .into_iter()
+            .map(|d| {
+                let Some(NumberOrString::String(code)) = d.code else {
+                    panic!("diagnostic without a code: {d:?}");
+                };
+                (
+                    code,
+                    &text[worker.document.line_numbers.from_range(&d.range)],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn defaults_match_literals() {
+        assert!(matches_default("true", &json!(true)));
+        assert!(matches_default("0.1", &json!(0.10000000149011612)));
+        assert!(matches_default("{ 1, 0, 0.5 }", &json!([1, 0, 0.5])));
+        assert!(matches_default("{\n}", &json!([])));
+        assert!(matches_default("\"\"", &json!("")));
+        assert!(matches_default("0x0000002a", &json!("0x2a")));
+        assert!(!matches_default("{ 1, 0 }", &json!([1, 0, 0])));
+        assert!(!matches_default("2", &json!(1)));
+    }
+
+    #[test]
+    fn duplicate_fields_and_keys() {
+        let text = format!(
+            r#"entries: map[hash,embed] = {{
+    "Characters/Test" = Outer {{
+        inner: embed = Inner {{
+            mValue: u32 = 1
+            mValue: u32 = 2
+        }}
+        other: embed = Inner {{
+            mValue: u32 = 3
+        }}
+        mNames: map[string,u32] = {{
+            "a" = 1
+            "A" = 2
+            "a" = 3
+        }}
+        mIds: map[hash,u32] = {{
+            "key" = 1
+            {} = 2
+        }}
+        mField: u32 = 1
+        {}: u32 = 2
+    }}
+}}
+"#,
+            hex("key"),
+            hex("mField"),
+        );
+        let worker = Fixture::default().worker(&text);
+        let collector = DiagnosticCollector {
+            server: &worker.server,
+            document: &worker.document,
+        };
+        let key = hex("key");
+        let field = hex("mField");
+        assert_eq!(
+            codes(&worker, collector.duplicate_keys(&worker.entries)),
+            [
+                ("duplicate-field".to_owned(), "mValue"),
+                ("duplicate-key".to_owned(), "\"a\""),
+                ("duplicate-key".to_owned(), key.as_str()),
+                ("duplicate-field".to_owned(), field.as_str()),
+            ]
+        );
+    }
+
+    fn spell_fixture() -> Fixture {
+        Fixture::default()
+            .class("SpellBase", None, &[("mId", "hash")])
+            .set("SpellBase", "defaults", json!({}))
+            .class(
+                "Spell",
+                Some("SpellBase"),
+                &[
+                    ("mEnabled", "bool"),
+                    ("mCount", "u32"),
+                    ("mColor", "vec3"),
+                    ("mName", "string"),
+                ],
+            )
+            .field_default("Spell", "mEnabled", json!(true))
+            .field_default("Spell", "mCount", json!(1))
+            .field_default("Spell", "mColor", json!([1, 0, 0.5]))
+    }
+
+    const SPELL: &str = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Spell {
+        mEnabled: bool = true
+        mCount: u32 = 2
+        mColor: vec3 = { 1, 0, 0.5 }
+    }
+}
+"#;
+
+    #[test]
+    fn fields_set_to_defaults() {
+        let worker = spell_fixture().worker(SPELL);
+        let collector = DiagnosticCollector {
+            server: &worker.server,
+            document: &worker.document,
+        };
+        let messages = collector
+            .default_values(&worker.entries)
+            .into_iter()
+            .map(|d| d.message)
+            .collect_vec();
+        assert_eq!(
+            messages,
+            [
+                "'mEnabled' is set to its default value",
+                "'mColor' is set to its default value",
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_never_set() {
+        let worker = spell_fixture().worker(SPELL);
+        let collector = DiagnosticCollector {
+            server: &worker.server,
+            document: &worker.document,
+        };
+        assert!(collector.missing_fields(&worker.entries).is_empty());
+
+        let worker = spell_fixture()
+            .option("diagnostics/missingFields", json!(true))
+            .worker(SPELL);
+        let collector = DiagnosticCollector {
+            server: &worker.server,
+            document: &worker.document,
+        };
+        let diagnostics = collector.missing_fields(&worker.entries);
+        assert_eq!(
+            codes(&worker, diagnostics.clone()),
+            [("missing-field".to_owned(), "Spell")]
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Fields with no default value are never set: mId, mName"
+        );
+    }
+
+    #[test]
+    fn fields_never_set_follow_the_instance_class() {
+        let worker = Fixture::default()
+            .class("Base", None, &[("mId", "hash"), ("mName", "string")])
+            .set("Base", "defaults", json!({}))
+            .class("Child", Some("Base"), &[("mName", "string")])
+            .set("Child", "defaults", json!({}))
+            .field_default("Child", "mId", json!(0))
+            .option("diagnostics/missingFields", json!(true))
+            .worker("entries: map[hash,embed] = {\n    \"Test\" = Child {}\n}\n");
+        let collector = DiagnosticCollector {
+            server: &worker.server,
+            document: &worker.document,
+        };
+        let messages = collector
+            .missing_fields(&worker.entries)
+            .into_iter()
+            .map(|d| d.message)
+            .collect_vec();
+        assert_eq!(
+            messages,
+            ["Fields with no default value are never set: mName"]
+        );
+    }
+
+    #[test]
+    fn schema_types_compare_parsed() {
+        let worker = Fixture::default()
+            .class(
+                "Spell",
+                None,
+                &[("mTags", "list[string]"), ("mScales", "map[hash,f32]")],
+            )
+            .worker(
+                r#"entries: map[hash,embed] = {
+    "Characters/Test" = Spell {
+        mTags: list[ string ] = {}
+        mScales: map[hash,u32] = {}
+    }
+}
+"#,
+            );
+        let collector = DiagnosticCollector {
+            server: &worker.server,
+            document: &worker.document,
+        };
+        let diagnostics = collector.schema_fields(&worker.entries);
+        assert_eq!(
+            codes(&worker, diagnostics.clone()),
+            [("schema-type-mismatch".to_owned(), "map[hash,u32]")]
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn duplicate_bin_objects() {
+        let key = hex("A");
+        let text =
+            format!("entries: map[hash,embed] = {{\n    \"A\" = X {{}}\n    {key} = X {{}}\n}}\n");
+        let worker = Fixture::default().worker(&text);
+        let found = worker
+            .diagnostics
+            .iter()
+            .filter_map(|d| match &d.code {
+                Some(NumberOrString::String(code)) => Some((
+                    code.as_str(),
+                    &text[worker.document.line_numbers.from_range(&d.range)],
+                )),
+                _ => None,
+            })
+            .collect_vec();
+        assert!(
+            found
+                .iter()
+                .any(|(code, at)| *at == key && ["duplicate-key", "shadowed-entry"].contains(code)),
+            "{found:?}"
+        );
+    }
+
+    #[test]
+    fn unresolved_links() {
+        let text = r#"entries: map[hash,embed] = {
+    "Local" = X {
+        links: list[link] = {
+            "Local"
+            "Elsewhere"
+            "Characters/Known"
+            ""
+            0x0
+            "Missing"
+            0x12345678
+            "Stale"
+        }
+    }
+}
+"#;
+        let objects =
+            |name: &str| format!("entries: map[hash,embed] = {{\n    \"{name}\" = X {{}}\n}}\n");
+        let worker = Fixture::default()
+            .names(&["Characters/Known"])
+            .workspace_file("file:///other.rito", &objects("Elsewhere"))
+            // what the open document used to define, as last saved
+            .workspace_file("file:///test.ritobin", &objects("Stale"))
+            .worker(text);
+        let links = worker
+            .unresolved_links
+            .iter()
+            .map(|span| &text[*span])
+            .collect_vec();
+        assert_eq!(links, ["\"Missing\"", "0x12345678", "\"Stale\""]);
+    }
+}
@@ -13,6 +13,8 @@ use crate::lsp::semantic_tokens::{
 pub struct SemanticVisitor<'a> {
     pub text: &'a str,
     pub line_nums: &'a LineNumbers,
+    /// Sorted spans of link values that don't resolve to any known entry
+    pub unresolved_links: &'a [Span],
     pub builder: SemanticTokensBuilder,
     pub stack: Vec<TreeKind>,
     pub range: Option<Span>,
@@ -58,6 +60,14 @@ impl Visitor for SemanticVisitor<'_> {
                 semantic_tokens::types::TYPE_PARAMETER
             }
             (TreeKind::Class, _) => semantic_tokens::types::CLASS,
+            (_, String | HexLit)
+                if self
+                    .unresolved_links
+                    .binary_search_by_key(&token.span.start, |s| s.start)
+                    .is_ok() =>
+            {
+                semantic_tokens::types::UNRESOLVED_REFERENCE
+            }
             (_, Name) => semantic_tokens::types::KEYWORD,
             (_, Quote) | (_, String) | (_, UnterminatedString) => semantic_tokens::types::STRING,
             (_, Number) | (_, HexLit) => semantic_tokens::types::NUMBER,
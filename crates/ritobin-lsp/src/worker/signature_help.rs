@@ -0,0 +1,368 @@
+use std::fmt::Write as _;
+
+use lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, Position, SignatureHelp,
+    SignatureInformation,
+};
+use ltk_ritobin::parse::{Span, TokenKind};
+use ritobin_lsp::syntax::{TypeExpr, hash_name};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    lol_meta::{
+        schema::{BinType, Property, U32Hash},
+        service::Classes,
+    },
+    worker::{Worker, completion::markdown, unhash::HashKind},
+};
+
+impl Worker {
+    /// The fields of the class whose block the cursor is in, or the element type and arity of
+    /// the container or vector literal.
+    pub fn signature_help(&self, position: Position) -> Option<SignatureHelp> {
+        let doc = &self.document;
+        let offset = doc.line_numbers.from_position(&position);
+        let classes = self.server.meta.classes.read();
+
+        let block = self.open_block(offset)?;
+        let signature = match block.class {
+            Some(class) => self.class_signature(offset, class, &classes)?,
+            None => self.literal_signature(offset, block.brace, &classes)?,
+        };
+        Some(SignatureHelp {
+            signatures: vec![signature],
+            active_signature: Some(0),
+            active_parameter: None,
+        })
+    }
+
+    /// `Class { field: type, .. }`, with the field on the cursor's line active.
+    fn class_signature(
+        &self,
+        offset: u32,
+        class: Span,
+        classes: &Classes,
+    ) -> Option<SignatureInformation> {
+        let text = self.document.text.as_str();
+        let class_hash = hash_name(&text[class]);
+        let active = hash_name(current_item(text, offset).split([':', '=']).next()?.trim());
+
+        let mut signature = Signature::new(
+            format!("{} {{ ", self.type_name(class_hash)),
+            self.server.config.caps().signature_help_label_offsets(),
+        );
+        let mut active_parameter = None;
+        let mut seen = FxHashSet::default();
+        for (hash, class) in classes.ancestors(class_hash) {
+            // each class's own fields in name order, like when filling in missing fields
+            let mut props = class
+                .properties
+                .iter()
+                .filter(|(k, _)| seen.insert(**k))
+                .map(|(k, prop)| {
+                    let name = self
+                        .unhashed(HashKind::Field, **k)
+                        .map_or_else(|| format!("0x{:08x}", **k), |name| name.to_owned());
+                    (name, *k, prop)
+                })
+                .collect::<Vec<_>>();
+            props.sort_by(|a, b| a.0.cmp(&b.0));
+            for (name, k, prop) in props {
+                if !signature.params.is_empty() {
+                    signature.label.push_str(", ");
+                }
+                if *k == active {
+                    active_parameter = Some(signature.params.len() as u32);
+                }
+                let docs = self.field_signature_docs(hash, *k, prop, classes);
+                signature.param(&format!("{name}: {}", prop.rito_type()), Some(docs));
+            }
+        }
+        signature.label.push_str(" }");
+
+        Some(SignatureInformation {
+            label: signature.label,
+            documentation: self.server.docs.class(class_hash).map(markdown),
+            parameters: Some(signature.params),
+            active_parameter,
+        })
+    }
+
+    fn field_signature_docs(
+        &self,
+        declaring: U32Hash,
+        field: u32,
+        prop: &Property,
+        classes: &Classes,
+    ) -> Documentation {
+        let mut str = String::new();
+        if let Some(other) = prop.other_class {
+            let _ = write!(str, "Class: `{}`\n\n", self.type_name(*other));
+        }
+        match self.server.docs.field(classes, *declaring, field) {
+            Some(docs) => str.push_str(docs),
+            None => {
+                let _ = write!(str, "Declared in `{}`", self.type_name(*declaring));
+            }
+        }
+        markdown(&str)
+    }
+
+    /// The element type and arity of a container, or the components of a vector, assigned by
+    /// the `=` before `brace`.
+    fn literal_signature(
+        &self,
+        offset: u32,
+        brace: Span,
+        classes: &Classes,
+    ) -> Option<SignatureInformation> {
+        let text = self.document.text.as_str();
+        let eq = self.block_eq(brace)?;
+        let (_, ty, prop) = self.field_header(eq, classes)?;
+        let rito_type = match prop {
+            Some(prop) => prop.rito_type().to_string(),
+            None => ty.to_owned(),
+        };
+        let ty = TypeExpr::parse(&rito_type);
+        let base = BinType::from_rito_name(ty.base)?;
+        let offsets = self.server.config.caps().signature_help_label_offsets();
+        let mut signature = Signature::new(format!("{rito_type} {{ "), offsets);
+
+        let (documentation, active_parameter) = match base {
+            BinType::Map => {
+                let (key, value) = (ty.args.first()?, ty.args.get(1)?);
+                signature.param(key, None);
+                signature.label.push_str(" = ");
+                signature.param(&self.element_label(value, prop), None);
+                signature.label.push_str(", …");
+
+                let active = current_item(text, offset).contains('=') as u32;
+                (format!("Any number of `{key} = {value}` entries"), active)
+            }
+            BinType::List | BinType::List2 | BinType::Option => {
+                let element = ty.value_type();
+                signature.param(&self.element_label(element, prop), None);
+                let fixed_size = prop.and_then(|p| p.container.as_ref()?.fixed_size);
+                let arity = match (base, fixed_size) {
+                    (BinType::Option, _) => "At most one item".to_owned(),
+                    (_, Some(1)) => "Exactly one item".to_owned(),
+                    (_, Some(size)) => format!("Exactly {size} items"),
+                    (_, None) => {
+                        signature.label.push_str(", …");
+                        "Any number of items".to_owned()
+                    }
+                };
+                (format!("{arity} of type `{element}`"), 0)
+            }
+            _ => {
+                let (components, component_type) = match base {
+                    BinType::Mtx44 => (MTX44_COMPONENTS, "f32"),
+                    BinType::Color => (base.components()?, "u8"),
+                    _ => (base.components()?, "f32"),
+                };
+                for (i, component) in components.iter().enumerate() {
+                    if i > 0 {
+                        signature.label.push_str(", ");
+                    }
+                    signature.param(&format!("{component}: {component_type}"), None);
+                }
+                let written = self
+                    .tokens
+                    .iter()
+                    .filter(|(t, _)| t.kind == TokenKind::Number)
+                    .filter(|(t, _)| brace.end <= t.span.start && t.span.end < offset)
+                    .count();
+                let active = written.min(components.len() - 1) as u32;
+                (
+                    format!(
+                        "{}\n\nExactly {} `{component_type}` values",
+                        base.description(),
+                        components.len()
+                    ),
+                    active,
+                )
+            }
+        };
+        signature.label.push_str(" }");
+
+        Some(SignatureInformation {
+            label: signature.label,
+            documentation: Some(markdown(&documentation)),
+            parameters: Some(signature.params),
+            active_parameter: Some(active_parameter),
+        })
+    }
+
+    /// `embed` or `pointer` elements read better as the class they should be an instance of.
+    fn element_label(&self, element: &str, prop: Option<&Property>) -> String {
+        match prop.and_then(|p| p.other_class) {
+            Some(class) => format!("{element} {}", self.type_name(*class)),
+            None => element.to_owned(),
+        }
+    }
+}
+
+const MTX44_COMPONENTS: &[&str] = &[
+    "m00", "m01", "m02", "m03", "m10", "m11", "m12", "m13", "m20", "m21", "m22", "m23", "m30",
+    "m31", "m32", "m33",
+];
+
+/// A signature label with its parameters, which are given as offsets into the label when the
+/// client supports it.
+struct Signature {
+    label: String,
+    params: Vec<ParameterInformation>,
+    label_offsets: bool,
+}
+
+impl Signature {
+    fn new(label: String, label_offsets: bool) -> Self {
+        Self {
+            label,
+            params: Vec::new(),
+            label_offsets,
+        }
+    }
+
+    fn param(&mut self, text: &str, documentation: Option<Documentation>) {
+        let start = self.label.encode_utf16().count() as u32;
+        self.label.push_str(text);
+        let end = self.label.encode_utf16().count() as u32;
+        let label = match self.label_offsets {
+            true => ParameterLabel::LabelOffsets([start, end]),
+            false => ParameterLabel::Simple(text.to_owned()),
+        };
+        self.params.push(ParameterInformation {
+            label,
+            documentation,
+        });
+    }
+}
+
+/// The text of the field or item being written at `offset`, from the start of its line or the
+/// `{` before it.
+fn current_item(text: &str, offset: u32) -> &str {
+    let before = &text[..offset as usize];
+    before[before.rfind(['\n', '{']).map_or(0, |i| i + 1)..].trim_start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::fixture::Fixture;
+
+    /// Signature help at the `$0` in `text`.
+    fn signature(fixture: Fixture, text: &str) -> SignatureHelp {
+        let offset = text.find("$0").expect("no cursor") as u32;
+        let worker = fixture.worker(&text.replace("$0", ""));
+        let position = worker.document.line_numbers.position(offset);
+        worker.signature_help(position).expect("no signature")
+    }
+
+    fn labels(signature: &SignatureInformation) -> Vec<String> {
+        signature
+            .parameters
+            .iter()
+            .flatten()
+            .map(|param| match &param.label {
+                ParameterLabel::Simple(label) => label.clone(),
+                ParameterLabel::LabelOffsets([start, end]) => signature
+                    .label
+                    .encode_utf16()
+                    .skip(*start as usize)
+                    .take((end - start) as usize)
+                    .map(|c| char::from_u32(c as u32).unwrap())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inherited_fields_listed_once() {
+        let fixture = Fixture::default()
+            .class("Base", None, &[("mName", "string"), ("mId", "u32")])
+            .class("Derived", Some("Base"), &[("mName", "string")]);
+        let help = signature(
+            fixture,
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Derived {
+        mId: $0
+    }
+}
+"#,
+        );
+        let signature = &help.signatures[0];
+        let mut params = labels(signature);
+        params.sort();
+        assert_eq!(params, ["mId: u32", "mName: string"]);
+        let active = signature.active_parameter.expect("no active field") as usize;
+        assert_eq!(labels(signature)[active], "mId: u32");
+    }
+
+    #[test]
+    fn fields_in_name_order_per_class() {
+        let fixture = Fixture::default()
+            .class("Base", None, &[("mZeta", "u32"), ("mAlpha", "u32")])
+            .class(
+                "Derived",
+                Some("Base"),
+                &[("mBeta", "u32"), ("mAardvark", "u32")],
+            );
+        let help = signature(
+            fixture,
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Derived {
+        $0
+    }
+}
+"#,
+        );
+        assert_eq!(
+            labels(&help.signatures[0]),
+            ["mAardvark: u32", "mBeta: u32", "mAlpha: u32", "mZeta: u32"]
+        );
+    }
+
+    #[test]
+    fn vector_components() {
+        let help = signature(
+            Fixture::default(),
+            r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mPos: vec3 = { 1, 2$0 }
+    }
+}
+"#,
+        );
+        let signature = &help.signatures[0];
+        assert_eq!(labels(signature), ["x: f32", "y: f32", "z: f32"]);
+        assert_eq!(signature.active_parameter, Some(1));
+    }
+
+    #[test]
+    fn map_key_then_value() {
+        let text = r#"entries: map[hash,embed] = {
+    "Characters/Test" = Holder {
+        mScales: map[hash,f32] = {
+            "key$0"
+        }
+    }
+}
+"#;
+        let help = signature(Fixture::default(), text);
+        let signature = &help.signatures[0];
+        assert_eq!(labels(signature), ["hash", "f32"]);
+        assert_eq!(signature.active_parameter, Some(0));
+
+        let help = signature(Fixture::default(), &text.replace("$0\"", "\" = $0"));
+        assert_eq!(help.signatures[0].active_parameter, Some(1));
+    }
+
+    #[test]
+    fn current_items() {
+        assert_eq!(current_item("a: vec3 = { 1, 2", 16), "1, 2");
+        assert_eq!(current_item("Foo {\n    mName: str", 20), "mName: str");
+        assert_eq!(current_item("mName", 5), "mName");
+        assert_eq!(current_item("Foo {\n    \"a\" = 1", 17), "\"a\" = 1");
+    }
+}
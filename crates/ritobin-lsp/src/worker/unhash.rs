@@ -1,88 +1,338 @@
 use lsp_types::{Range, TextEdit};
+use ltk_hash::fnv1a;
 use ltk_ritobin::{
-    cst::{
-        Visitor,
-        visitor::{Visit, VisitorExt},
-    },
-    parse::{Span, TokenKind},
+    cst::Kind as TreeKind,
+    parse::{Span, Token, TokenKind},
 };
-use poro_hash::{BinHash, FromStrRadix};
+use poro_hash::{BinHash, FromStrRadix, Hashtable};
+use ritobin_lsp::syntax::{TypeExpr, quote, unquote};
+use xxhash_rust::xxh64::xxh64;
 
-use crate::{server::Hashes, worker::Worker};
+use crate::{
+    lol_meta::{schema::BinType, service::Classes},
+    worker::Worker,
+};
+
+/// The hash tables a hashed name can come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashKind {
+    Type,
+    Field,
+    Entry,
+    Hash,
+    /// Game file paths, which are xxh64 rather than fnv1a hashes
+    File,
+}
+
+impl HashKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            HashKind::Type => "Class name",
+            HashKind::Field => "Field name",
+            HashKind::Entry => "Entry name",
+            HashKind::Hash => "Hash",
+            HashKind::File => "File path",
+        }
+    }
+
+    /// The file in the hashes directory that names hashes of this kind.
+    pub fn table_file(self) -> Option<&'static str> {
+        match self {
+            HashKind::Type => Some("hashes.bintypes.txt"),
+            HashKind::Field => Some("hashes.binfields.txt"),
+            HashKind::Entry => Some("hashes.binentries.txt"),
+            HashKind::Hash => Some("hashes.binhashes.txt"),
+            HashKind::File => None,
+        }
+    }
+}
 
 impl Worker {
-    pub fn unhash(&self, _range: Option<Range>) -> anyhow::Result<Option<Vec<TextEdit>>> {
-        let Some((cst, _)) = self.bin.as_ref() else {
+    pub fn unhash(&self, range: Option<Range>) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        if self.bin.is_none() {
             return Ok(None);
-        };
+        }
 
-        let unhasher = Unhasher::new(&self.server.hashes, &self.document.text).walk(cst);
+        let text = self.document.text.as_str();
+        // an empty selection unhashes the whole file
+        let range = range
+            .map(|r| self.document.line_numbers.from_range(&r))
+            .filter(|r| r.start != r.end);
+        let classes = self.server.meta.classes.read();
 
-        Ok(Some(
-            unhasher
-                .edits
-                .into_iter()
-                .map(|e| TextEdit {
-                    range: self.document.line_numbers.from_span(e.0),
-                    new_text: e.1.to_string(),
+        let edits = self
+            .tokens
+            .iter()
+            .filter(|(token, _)| token.kind == TokenKind::HexLit)
+            .filter(|(token, _)| {
+                range.is_none_or(|r| token.span.start < r.end && r.start < token.span.end)
+            })
+            .filter_map(|(token, tree)| {
+                let hash =
+                    BinHash::from_str_radix(text[token.span].strip_prefix("0x")?, 16).ok()?;
+                let (table, quoted) = self.hash_table(token, *tree, &classes)?;
+                let name = table.hashes.get(&hash)?;
+                Some(TextEdit {
+                    range: self.document.line_numbers.from_span(token.span),
+                    new_text: match quoted {
+                        true => quote(name),
+                        false => name.clone(),
+                    },
                 })
-                .collect(),
-        ))
+            })
+            .collect();
+
+        Ok(Some(edits))
     }
-}
 
-struct Unhasher<'a> {
-    hashes: &'a Hashes,
-    txt: &'a str,
-    edits: Vec<(Span, &'a str)>,
-}
+    pub fn rehash(&self, range: Option<Range>) -> Vec<TextEdit> {
+        // an empty selection rehashes the whole file
+        let span = range
+            .map(|r| self.document.line_numbers.from_range(&r))
+            .filter(|r| r.start != r.end);
+        self.rehash_edits(span)
+    }
 
-impl<'a> Unhasher<'a> {
-    pub fn new(hashes: &'a Hashes, txt: &'a str) -> Self {
-        Self {
-            hashes,
-            txt,
-            edits: vec![],
-        }
+    /// Replace the names within `span` (or the whole file) with the `0x..` hashes they stand
+    /// for.
+    pub fn rehash_edits(&self, span: Option<Span>) -> Vec<TextEdit> {
+        let text = self.document.text.as_str();
+        let classes = self.server.meta.classes.read();
+
+        self.rehashable(span, &classes)
+            .map(|(token, kind)| {
+                let name = unquote(&text[token.span]);
+                let new_text = match kind {
+                    HashKind::File => {
+                        format!("0x{:016x}", xxh64(name.to_ascii_lowercase().as_bytes(), 0))
+                    }
+                    _ => format!("0x{:08x}", fnv1a::hash_lower(name)),
+                };
+                TextEdit {
+                    range: self.document.line_numbers.from_span(token.span),
+                    new_text,
+                }
+            })
+            .collect()
+    }
+
+    /// The names within `span` (or the whole file) that stand for a hash, and what kind of hash.
+    pub(crate) fn rehashable<'a>(
+        &'a self,
+        span: Option<Span>,
+        classes: &'a Classes,
+    ) -> impl Iterator<Item = (&'a Token, HashKind)> + 'a {
+        let text = self.document.text.as_str();
+
+        self.tokens
+            .iter()
+            .filter(|(token, tree)| match tree {
+                TreeKind::Class | TreeKind::EntryKey => {
+                    matches!(token.kind, TokenKind::Name | TokenKind::String)
+                }
+                // bare words in values are keywords like `true` and `null`
+                _ => token.kind == TokenKind::String,
+            })
+            .filter(move |(token, _)| {
+                span.is_none_or(|s| token.span.start < s.end && s.start < token.span.end)
+            })
+            .filter(move |(token, _)| !unquote(&text[token.span]).is_empty())
+            .filter_map(move |(token, tree)| Some((token, self.hash_kind(token, *tree, classes)?)))
+    }
+
+    /// The table a hex literal should be unhashed with, and whether its name has to be quoted.
+    ///
+    /// Class names and field names are bare words, everything else is a string.
+    fn hash_table(
+        &self,
+        token: &Token,
+        tree: TreeKind,
+        classes: &Classes,
+    ) -> Option<(&Hashtable<BinHash>, bool)> {
+        let kind = self.hash_kind(token, tree, classes)?;
+        let quoted = !matches!(kind, HashKind::Type | HashKind::Field);
+        Some((self.hash_table_for(kind)?, quoted))
     }
-}
 
-impl<'a> Visitor for Unhasher<'a> {
-    fn visit_token(
-        &mut self,
-        token: &ltk_ritobin::parse::Token,
-        context: &ltk_ritobin::Cst,
-    ) -> Visit {
-        if token.kind != TokenKind::HexLit {
-            return Visit::Continue;
+    pub(crate) fn hash_table_for(&self, kind: HashKind) -> Option<&Hashtable<BinHash>> {
+        let hashes = &self.server.hashes;
+        match kind {
+            HashKind::Type => hashes.types.as_ref(),
+            HashKind::Field => hashes.fields.as_ref(),
+            HashKind::Entry => hashes.entries.as_ref(),
+            HashKind::Hash => hashes.hashes.as_ref(),
+            // there's no table of game file paths
+            HashKind::File => None,
         }
+    }
 
-        eprintln!("[unhash] {:?}", context.kind);
-        let Some(txt) = &self.txt[token.span].strip_prefix("0x") else {
-            return Visit::Continue;
-        };
+    /// What kind of hash a token is, or can be converted to.
+    pub(crate) fn hash_kind(
+        &self,
+        token: &Token,
+        tree: TreeKind,
+        classes: &Classes,
+    ) -> Option<HashKind> {
+        let text = self.document.text.as_str();
 
-        let unhashed = match context.kind {
-            ltk_ritobin::cst::Kind::EntryKey => {
-                let Some(k) = BinHash::from_str_radix(txt, 16).ok() else {
-                    return Visit::Continue;
-                };
-                self.hashes.fields.as_ref().and_then(|h| h.hashes.get(&k))
+        let ty = match tree {
+            TreeKind::Class => return Some(HashKind::Type),
+            TreeKind::EntryKey => {
+                let entry = self.entries.entries.iter().find(|e| e.key == token.span)?;
+                if entry.is_field() {
+                    // top-level entries are section names, not hashes
+                    return entry.block.map(|_| HashKind::Field);
+                }
+                let parent = entry.parent?;
+                if self
+                    .entries
+                    .is_objects_map(&self.entries.entries[parent], text)
+                {
+                    return Some(HashKind::Entry);
+                }
+                self.element_type(parent, classes, true)?
             }
-            ltk_ritobin::cst::Kind::Class => {
-                let Some(k) = BinHash::from_str_radix(txt, 16).ok() else {
-                    return Visit::Continue;
-                };
-                self.hashes.types.as_ref().and_then(|h| h.hashes.get(&k))
+            _ => {
+                let (idx, entry) = self.entries.at(token.span.start)?;
+                match entry.is_field() {
+                    true => self.element_type(idx, classes, false)?,
+                    // map values are typed by the map
+                    false => self.element_type(entry.parent?, classes, false)?,
+                }
             }
-            _ => return Visit::Continue,
         };
 
-        if let Some(unhashed) = unhashed {
-            self.edits.push((token.span, unhashed.as_str()));
+        match ty {
+            BinType::Hash => Some(HashKind::Hash),
+            BinType::Link => Some(HashKind::Entry),
+            BinType::File => Some(HashKind::File),
+            _ => None,
         }
-        eprintln!("[unhash] -> {unhashed:?}");
+    }
+
+    /// The type of a field's map keys (if `key`), or of its values, container items or map
+    /// values.
+    ///
+    /// This comes from the class schema when we know it, otherwise from the type expression.
+    pub(crate) fn element_type(&self, idx: usize, classes: &Classes, key: bool) -> Option<BinType> {
+        let text = self.document.text.as_str();
+        let entry = &self.entries.entries[idx];
+
+        if let Some(prop) = self.property(entry, classes) {
+            return match (key, &prop.map, &prop.container) {
+                (true, Some(map), _) => Some(map.key_type),
+                (true, None, _) => None,
+                (false, Some(map), _) => Some(map.value_type),
+                (false, None, Some(container)) => Some(container.value_type),
+                (false, None, None) => Some(prop.value_type),
+            };
+        }
+
+        let ty = TypeExpr::parse(&text[entry.type_expr?]);
+        let name = match key {
+            true => *ty.args.first().filter(|_| ty.is_map())?,
+            false => ty.value_type(),
+        };
+        BinType::from_rito_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::fixture::{Fixture, hex};
 
-        Visit::Continue
+    fn fixture() -> Fixture {
+        Fixture::default()
+            .names_in(HashKind::Entry, &["Characters/Test", "Characters/Linked"])
+            .names_in(HashKind::Type, &["Spell"])
+            .names_in(HashKind::Field, &["mValue"])
+            .names_in(HashKind::Hash, &["HashValue", "MapKey"])
+            // in every table, so only the kind keeps it from being unhashed
+            .names(&["Data/File.bin"])
+    }
+
+    fn text() -> String {
+        format!(
+            r#"entries: map[hash,embed] = {{
+    {} = {} {{
+        {}: hash = {}
+        mLink: link = {}
+        mIds: map[hash,u32] = {{
+            {} = 1
+        }}
+        mFile: file = {}
+    }}
+}}
+"#,
+            hex("Characters/Test"),
+            hex("Spell"),
+            hex("mValue"),
+            hex("HashValue"),
+            hex("Characters/Linked"),
+            hex("MapKey"),
+            hex("Data/File.bin"),
+        )
+    }
+
+    fn new_texts(edits: Vec<TextEdit>) -> Vec<String> {
+        edits.into_iter().map(|e| e.new_text).collect()
+    }
+
+    #[test]
+    fn names_come_from_the_table_for_their_kind() {
+        let worker = fixture().worker(&text());
+        let edits = worker.unhash(None).unwrap().unwrap();
+        assert_eq!(
+            new_texts(edits),
+            [
+                "\"Characters/Test\"",
+                "Spell",
+                "mValue",
+                "\"HashValue\"",
+                "\"Characters/Linked\"",
+                "\"MapKey\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn unhash_respects_the_range() {
+        let text = text();
+        let worker = fixture().worker(&text);
+        let line = text.lines().nth(2).unwrap();
+        let start = text.find(line).unwrap();
+        let span = Span::new(start as u32, (start + line.len()) as u32);
+        let range = worker.document.line_numbers.from_span(span);
+        let edits = worker.unhash(Some(range)).unwrap().unwrap();
+        assert_eq!(new_texts(edits), ["mValue", "\"HashValue\""]);
+    }
+
+    #[test]
+    fn rehash_names_by_kind() {
+        let text = r#"section: u32 = 1
+entries: map[hash,embed] = {
+    "Characters/Test" = Spell {
+        mValue: hash = "HashValue"
+        mLink: link = "Characters/Linked"
+        mFile: file = "Data/File.bin"
+    }
+}
+"#;
+        let worker = Fixture::default().worker(text);
+        let file = format!("0x{:016x}", xxh64(b"data/file.bin", 0));
+        assert_eq!(
+            new_texts(worker.rehash_edits(None)),
+            [
+                hex("Characters/Test"),
+                hex("Spell"),
+                hex("mValue"),
+                hex("HashValue"),
+                hex("mLink"),
+                hex("Characters/Linked"),
+                hex("mFile"),
+                file,
+            ]
+        );
     }
 }
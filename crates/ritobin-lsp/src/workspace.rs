@@ -0,0 +1,448 @@
+use std::{
+    collections::hash_map,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use dashmap::RwLock;
+use lsp_types::{
+    Diagnostic, FullDocumentDiagnosticReport, PreviousResultId, UnchangedDocumentDiagnosticReport,
+    Url, WorkspaceDiagnosticReport, WorkspaceDocumentDiagnosticReport,
+    WorkspaceFullDocumentDiagnosticReport, WorkspaceUnchangedDocumentDiagnosticReport,
+};
+use ltk_ritobin::Cst;
+use ltk_ritobin::parse::TokenKind;
+use ritobin_lsp::syntax::{Entries, KeyId, Tokens, is_asset_path, unquote};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    document::Document,
+    server::Server,
+    worker::diagnostics::{self, DiagnosticCollector},
+};
+
+pub const FILE_EXTENSION: &str = "rito";
+
+/// A ritobin file in one of the workspace roots, as last read from disk.
+pub struct IndexedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    text: String,
+    /// Path hashes of the bin objects defined in this file, with their paths unless they were
+    /// written as hashes
+    pub objects: FxHashMap<u32, Option<String>>,
+    /// Hashes of the bin objects linked to from this file, once per link
+    pub links: Vec<u32>,
+    /// String literals that look like asset paths
+    pub asset_paths: FxHashSet<String>,
+    pub result_id: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Index of every ritobin file in the workspace roots, so we can report on files that aren't open.
+#[derive(Default)]
+pub struct WorkspaceIndex {
+    files: RwLock<FxHashMap<Url, IndexedFile>>,
+    /// How many indexed files define each bin object, so links can be resolved without going
+    /// through every file
+    object_counts: RwLock<FxHashMap<u32, usize>>,
+}
+
+impl WorkspaceIndex {
+    /// Rescan the workspace roots, re-reading any file that changed since the last scan.
+    ///
+    /// This walks every root, so it is only done on startup. Afterwards the client tells us
+    /// which files changed.
+    pub fn refresh(&self, server: &Server) -> bool {
+        let mut paths = Vec::new();
+        for root in server.config.workspace_roots() {
+            collect_files(root.as_ref(), &mut paths);
+        }
+        // files that are gone since the last scan
+        let seen = paths
+            .iter()
+            .filter_map(|path| Url::from_file_path(path).ok())
+            .collect::<FxHashSet<_>>();
+        let removed = self
+            .files
+            .read()
+            .keys()
+            .filter(|uri| !seen.contains(*uri))
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect::<Vec<_>>();
+        paths.extend(removed);
+        self.update(server, paths)
+    }
+
+    /// Re-read `paths`, dropping the ones that no longer exist, and re-check the files that
+    /// are affected. Returns whether the set of bin objects changed.
+    pub fn update(&self, server: &Server, paths: impl IntoIterator<Item = PathBuf>) -> bool {
+        let mut changed = Vec::new();
+        let mut objects_changed = false;
+        for path in paths {
+            if path.extension().is_none_or(|ext| ext != FILE_EXTENSION) {
+                continue;
+            }
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let Ok(meta) = fs::metadata(&path) else {
+                if let Some(file) = self.files.write().remove(&uri) {
+                    objects_changed |= !file.objects.is_empty();
+                    self.remove_objects(&file);
+                }
+                continue;
+            };
+
+            let modified = meta.modified().ok();
+            if self
+                .files
+                .read()
+                .get(&uri)
+                .is_some_and(|f| f.modified == modified && f.len == meta.len())
+            {
+                continue;
+            }
+
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    tracing::warn!("Failed to read workspace file {path:?} - {e}");
+                    continue;
+                }
+            };
+
+            let file = IndexedFile::new(text, modified, meta.len());
+            objects_changed |= self.insert_file(uri.clone(), file);
+            changed.push(uri);
+        }
+
+        // links in other files may resolve differently now, so everything needs re-checking
+        if objects_changed {
+            self.recheck(server);
+        } else {
+            for uri in changed {
+                self.check(server, &uri);
+            }
+        }
+        objects_changed
+    }
+
+    /// Index `file` as `uri`, replacing what was indexed for it before. Returns whether the set
+    /// of bin objects changed.
+    fn insert_file(&self, uri: Url, file: IndexedFile) -> bool {
+        let mut files = self.files.write();
+        let objects_changed = files.get(&uri).is_none_or(|f| f.objects != file.objects);
+        self.add_objects(&file);
+        if let Some(old) = files.insert(uri, file) {
+            self.remove_objects(&old);
+        }
+        objects_changed
+    }
+
+    fn add_objects(&self, file: &IndexedFile) {
+        let mut counts = self.object_counts.write();
+        for hash in file.objects.keys() {
+            *counts.entry(*hash).or_default() += 1;
+        }
+    }
+
+    fn remove_objects(&self, file: &IndexedFile) {
+        let mut counts = self.object_counts.write();
+        for hash in file.objects.keys() {
+            if let hash_map::Entry::Occupied(mut count) = counts.entry(*hash) {
+                *count.get_mut() -= 1;
+                if *count.get() == 0 {
+                    count.remove();
+                }
+            }
+        }
+    }
+
+    /// Re-check every file, e.g. once the meta dump has loaded.
+    pub fn recheck(&self, server: &Server) {
+        let uris = self.files.read().keys().cloned().collect::<Vec<_>>();
+        for uri in uris {
+            self.check(server, &uri);
+        }
+    }
+
+    fn check(&self, server: &Server, uri: &Url) {
+        let Some(text) = self.files.read().get(uri).map(|f| f.text.clone()) else {
+            return;
+        };
+        let document = Document::new(uri.clone(), 0, text);
+        let diagnostics = file_diagnostics(server, &document);
+        if let Some(file) = self.files.write().get_mut(uri) {
+            file.result_id = diagnostics::result_id(&diagnostics);
+            file.diagnostics = diagnostics;
+        }
+    }
+
+    /// Index `text` as the contents of `uri`, as if read from disk.
+    #[cfg(test)]
+    pub fn insert(&self, server: &Server, uri: Url, text: &str) {
+        let file = IndexedFile::new(text.to_owned(), None, text.len() as u64);
+        self.insert_file(uri.clone(), file);
+        self.check(server, &uri);
+    }
+
+    /// Whether any indexed file other than `except` defines a bin object with this path hash.
+    pub fn contains_object(&self, hash: u32, except: &Url) -> bool {
+        let Some(count) = self.object_counts.read().get(&hash).copied() else {
+            return false;
+        };
+        let own = self
+            .files
+            .read()
+            .get(except)
+            .is_some_and(|f| f.objects.contains_key(&hash));
+        count > usize::from(own)
+    }
+
+    /// The bin objects defined in indexed files other than `except`, as in
+    /// [`IndexedFile::objects`].
+    pub fn objects(&self, except: &Url) -> FxHashMap<u32, Option<String>> {
+        let mut objects = FxHashMap::default();
+        for (_, file) in self.files.read().iter().filter(|(uri, _)| *uri != except) {
+            for (hash, name) in &file.objects {
+                let known = objects.entry(*hash).or_insert(None);
+                if known.is_none() {
+                    *known = name.clone();
+                }
+            }
+        }
+        objects
+    }
+
+    /// Every asset path written in an indexed file other than `except`.
+    pub fn asset_paths(&self, except: &Url) -> FxHashSet<String> {
+        self.files
+            .read()
+            .iter()
+            .filter(|(uri, _)| *uri != except)
+            .flat_map(|(_, f)| f.asset_paths.iter().cloned())
+            .collect()
+    }
+
+    /// How many links to the bin object with this path hash there are in indexed files other
+    /// than `except`.
+    pub fn link_count(&self, hash: u32, except: &Url) -> usize {
+        self.files
+            .read()
+            .iter()
+            .filter(|(uri, _)| *uri != except)
+            .map(|(_, f)| f.links.iter().filter(|l| **l == hash).count())
+            .sum()
+    }
+
+    /// Diagnostics for every indexed file that isn't open in the editor.
+    ///
+    /// Open documents report their own diagnostics via `textDocument/diagnostic`.
+    pub fn diagnostics(
+        &self,
+        open: &FxHashSet<Url>,
+        previous_result_ids: &[PreviousResultId],
+    ) -> WorkspaceDiagnosticReport {
+        let previous = previous_result_ids
+            .iter()
+            .map(|p| (&p.uri, p.value.as_str()))
+            .collect::<FxHashMap<_, _>>();
+
+        let files = self.files.read();
+        let items = files
+            .iter()
+            .filter(|(uri, _)| !open.contains(*uri))
+            .map(|(uri, file)| {
+                if previous.get(uri) == Some(&file.result_id.as_str()) {
+                    WorkspaceDocumentDiagnosticReport::Unchanged(
+                        WorkspaceUnchangedDocumentDiagnosticReport {
+                            uri: uri.clone(),
+                            version: None,
+                            unchanged_document_diagnostic_report:
+                                UnchangedDocumentDiagnosticReport {
+                                    result_id: file.result_id.clone(),
+                                },
+                        },
+                    )
+                } else {
+                    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                        uri: uri.clone(),
+                        version: None,
+                        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                            result_id: Some(file.result_id.clone()),
+                            items: file.diagnostics.clone(),
+                        },
+                    })
+                }
+            })
+            .collect();
+
+        WorkspaceDiagnosticReport { items }
+    }
+}
+
+impl IndexedFile {
+    fn new(text: String, modified: Option<SystemTime>, len: u64) -> Self {
+        let cst = Cst::parse(&text);
+        let entries = Entries::new(&cst);
+        let tokens = Tokens::new(&cst);
+        let objects = entries
+            .objects(&text)
+            .filter_map(|(_, e)| {
+                let name = (e.key_kind == TokenKind::String).then(|| unquote(&text[e.key]).into());
+                Some((e.key_hash(&text)?, name))
+            })
+            .collect();
+        let asset_paths = tokens
+            .iter()
+            .filter(|(t, _)| t.kind == TokenKind::String)
+            .map(|(t, _)| unquote(&text[t.span]))
+            .filter(|s| is_asset_path(s))
+            .map(str::to_owned)
+            .collect();
+        let links = entries
+            .link_values(&tokens, &text)
+            .into_iter()
+            .filter_map(|t| match KeyId::new(&text[t.span], t.kind, true) {
+                KeyId::Hash(hash) => Some(hash),
+                KeyId::Text(_) => None,
+            })
+            .collect();
+
+        Self {
+            modified,
+            len,
+            objects,
+            links,
+            asset_paths,
+            text,
+            result_id: String::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+/// A file listed in a WAD, with its path lowercased for matching against.
+pub struct WadPath {
+    pub path: String,
+    pub lower: String,
+}
+
+/// Read a listing of the files in a WAD, one path per line.
+///
+/// Lines may also be prefixed by the path's hash, as in CommunityDragon's `hashes.game.txt`.
+pub fn load_wad_listing(path: &Path) -> anyhow::Result<Vec<WadPath>> {
+    Ok(parse_wad_listing(&fs::read_to_string(path)?))
+}
+
+fn parse_wad_listing(text: &str) -> Vec<WadPath> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .map(|line| match line.split_once(' ') {
+            // paths can have spaces in them, so only a leading xxh64 hash is split off
+            Some((hash, path))
+                if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                path
+            }
+            _ => line,
+        })
+        .filter(|path| !path.is_empty())
+        .map(|path| WadPath {
+            path: path.to_owned(),
+            lower: path.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+fn file_diagnostics(server: &Server, document: &Document) -> Vec<Diagnostic> {
+    let cst = Cst::parse(&document.text);
+    let (_bin, errors) = cst.build_bin(&document.text);
+    let entries = Entries::new(&cst);
+    let tokens = Tokens::new(&cst);
+    let collector = DiagnosticCollector { server, document };
+    let links = collector.unresolved_links(&entries, &tokens);
+    collector.collect(&cst, &entries, &links, errors)
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                collect_files(&path, out);
+            }
+        } else if path.extension().is_some_and(|ext| ext == FILE_EXTENSION) {
+            out.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::fixture::Fixture;
+
+    #[test]
+    fn wad_listings() {
+        let paths = parse_wad_listing(
+            "0123456789abcdef assets/Characters/Ahri/Ahri.skn\r\ndata/menu/en_us/Main Menu.txt\n\nfedcba9876543210 assets/Shared/My Texture.dds\n",
+        );
+        let paths = paths.iter().map(|p| p.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "assets/Characters/Ahri/Ahri.skn",
+                "data/menu/en_us/Main Menu.txt",
+                "assets/Shared/My Texture.dds",
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_files_report_their_result_id() {
+        let server = Fixture::default().server();
+        let uri = Url::parse("file:///other.rito").unwrap();
+        server
+            .workspace
+            .insert(&server, uri.clone(), "a: u32 = \"str\"\n");
+
+        let report = server.workspace.diagnostics(&FxHashSet::default(), &[]);
+        let [WorkspaceDocumentDiagnosticReport::Full(full)] = &report.items[..] else {
+            panic!("expected a full report: {report:?}");
+        };
+        assert!(!full.full_document_diagnostic_report.items.is_empty());
+        let result_id = full
+            .full_document_diagnostic_report
+            .result_id
+            .clone()
+            .unwrap();
+
+        let previous = [PreviousResultId {
+            uri: uri.clone(),
+            value: result_id.clone(),
+        }];
+        let report = server
+            .workspace
+            .diagnostics(&FxHashSet::default(), &previous);
+        let [WorkspaceDocumentDiagnosticReport::Unchanged(unchanged)] = &report.items[..] else {
+            panic!("expected an unchanged report: {report:?}");
+        };
+        assert_eq!(
+            unchanged.unchanged_document_diagnostic_report.result_id,
+            result_id
+        );
+
+        // open documents report their own diagnostics
+        let open = FxHashSet::from_iter([uri]);
+        assert!(server.workspace.diagnostics(&open, &[]).items.is_empty());
+    }
+}
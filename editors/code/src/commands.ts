@@ -3,7 +3,12 @@ import * as lc from "vscode-languageclient";
 import * as ra from "./lsp_ext";
 import * as path from "path";
 
-import { applySnippetTextEdits } from "./snippets";
+import {
+  applySnippetTextEdits,
+  applySnippetWorkspaceEdit,
+  type SnippetTextDocumentEdit,
+  toSnippetTextEdits,
+} from "./snippets";
 
 import type { Cmd, CtxInit } from "./ctx";
 import { log } from "./util";
@@ -68,6 +73,82 @@ export function unhash(ctx: CtxInit): Cmd {
   };
 }
 
+export function rehash(ctx: CtxInit): Cmd {
+  return async () => {
+    const editor = ctx.activeRitobinEditor;
+    if (!editor) return;
+    const client = ctx.client;
+
+    const lcEdits = await client.sendRequest(ra.rehash, {
+      range: client.code2ProtocolConverter.asRange(editor.selection),
+      textDocument: client.code2ProtocolConverter.asTextDocumentIdentifier(
+        editor.document,
+      ),
+    });
+
+    if (!lcEdits) return;
+
+    const edits = await client.protocol2CodeConverter.asTextEdits(lcEdits);
+    await applySnippetTextEdits(editor, edits);
+  };
+}
+
+export function resolveCodeAction(ctx: CtxInit): Cmd {
+  return async (params: lc.CodeAction) => {
+    const client = ctx.client;
+    params.command = undefined;
+    const item = await client.sendRequest(
+      lc.CodeActionResolveRequest.type,
+      params,
+    );
+    if (!item?.edit) {
+      return;
+    }
+    const itemEdit = item.edit;
+    // apply everything but the text document edits as-is, so that snippets in the text edits
+    // can be converted to `vscode.SnippetTextEdit`s below
+    const lcFileSystemEdit = {
+      ...itemEdit,
+      documentChanges: itemEdit.documentChanges?.filter(
+        (change) => "kind" in change,
+      ),
+    };
+    const fileSystemEdit =
+      await client.protocol2CodeConverter.asWorkspaceEdit(lcFileSystemEdit);
+    await vscode.workspace.applyEdit(fileSystemEdit);
+
+    const snippetTextDocumentEdits: SnippetTextDocumentEdit[] = [];
+    for (const change of itemEdit.documentChanges ?? []) {
+      if (lc.TextDocumentEdit.is(change)) {
+        const uri = client.protocol2CodeConverter.asUri(
+          change.textDocument.uri,
+        );
+        const edits = await client.protocol2CodeConverter.asTextEdits(
+          change.edits,
+        );
+        snippetTextDocumentEdits.push([uri, toSnippetTextEdits(edits)]);
+      }
+    }
+    if (snippetTextDocumentEdits.length > 0) {
+      await applySnippetWorkspaceEdit(
+        new vscode.WorkspaceEdit(),
+        snippetTextDocumentEdits,
+      );
+    }
+  };
+}
+
+export function applyActionGroup(_ctx: CtxInit): Cmd {
+  return async (actions: { label: string; arguments: lc.CodeAction }[]) => {
+    const selectedAction = await vscode.window.showQuickPick(actions);
+    if (!selectedAction) return;
+    await vscode.commands.executeCommand(
+      "ritobin-lsp.resolveCodeAction",
+      selectedAction.arguments,
+    );
+  };
+}
+
 export function onEnter(ctx: CtxInit): Cmd {
   async function handleKeypress() {
     const editor = ctx.activeRitobinEditor;
@@ -34,6 +34,10 @@ export const unhash = new lc.RequestType<UnhashParams, lc.TextEdit[], void>(
   "ritobin-lsp/unhash",
 );
 
+export const rehash = new lc.RequestType<RehashParams, lc.TextEdit[], void>(
+  "ritobin-lsp/rehash",
+);
+
 export const analyzerStatus = new lc.RequestType<
   AnalyzerStatusParams,
   string,
@@ -116,6 +120,10 @@ export type UnhashParams = {
   textDocument: lc.TextDocumentIdentifier;
   range?: lc.Range | null;
 };
+export type RehashParams = {
+  textDocument: lc.TextDocumentIdentifier;
+  range?: lc.Range | null;
+};
 
 export type AnalyzerStatusParams = { textDocument?: lc.TextDocumentIdentifier };
 
@@ -169,6 +169,9 @@ function createCommands(): Record<string, CommandFactory> {
     },
     lspStatus: { enabled: commands.lspStatus },
     unhash: { enabled: commands.unhash },
+    rehash: { enabled: commands.rehash },
+    resolveCodeAction: { enabled: commands.resolveCodeAction },
+    applyActionGroup: { enabled: commands.applyActionGroup },
     matchingBrace: {
       enabled: (_) => async () => {},
       disabled: (_) => async () => {},
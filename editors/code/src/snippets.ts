@@ -65,7 +65,7 @@ function hasSnippet(snip: string): boolean {
   return m != null;
 }
 
-function toSnippetTextEdits(
+export function toSnippetTextEdits(
   edits: vscode.TextEdit[],
 ): (vscode.TextEdit | vscode.SnippetTextEdit)[] {
   return edits.map((textEdit) => {